@@ -0,0 +1,21 @@
+//! Draws a single Shadertoy style fragment shader from `shaders/` to a window.
+//!
+//! `cargo run --example fragment_shader -- noise.frag` picks another shader, editing the shader while it runs
+//! reloads it.
+
+use anyhow::Result;
+
+use rust_vulkan_engine::*;
+
+fn main() -> Result<()> {
+    pretty_env_logger::init();
+
+    let config = Config {
+        title: "Fragment shader".to_string(),
+        shader: Some(std::env::args().nth(1).unwrap_or_else(|| "interactive.frag".to_string())),
+        shader_dir: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders").into()),
+        ..Default::default()
+    };
+
+    run(&config, Vec::new())
+}
@@ -0,0 +1,37 @@
+//! Renders `shaders/mesh.scene`, which draws `cube.obj` with `mesh.vert` and `mesh.frag`, and counts the frames
+//! with a draw hook.
+
+use anyhow::Result;
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use rust_vulkan_engine::*;
+
+/// Logs how many frames were drawn every few seconds, a hook could as well bind its own pipeline and draw.
+#[derive(Debug, Default)]
+struct FrameCounter {
+    frames: u32,
+}
+
+impl DrawHook for FrameCounter {
+    fn record(&mut self, _device: &Device, context: &DrawContext) {
+        self.frames += 1;
+        if self.frames.is_multiple_of(1000) {
+            info!("{} frames drawn at {}x{}", self.frames, context.extent.width, context.extent.height);
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    pretty_env_logger::init();
+
+    let config = Config {
+        title: "Mesh".to_string(),
+        scene: Some("mesh.scene".to_string()),
+        shader_dir: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders").into()),
+        msaa_samples: vk::SampleCountFlags::_4,
+        ..Default::default()
+    };
+
+    run(&config, vec![Box::new(FrameCounter::default())])
+}
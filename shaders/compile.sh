@@ -1,3 +0,0 @@
-glslc_bin="/home/neutronic/glslc/bin/glslc"
-"$glslc_bin" shader.vert -o vert.spv
-"$glslc_bin" shader.frag -o frag.spv
\ No newline at end of file
@@ -0,0 +1,22 @@
+// Shadertoy style: a mainImage and no version directive, the prelude declares the inputs.
+// Drag with the left mouse button to move the circle, hold space to grow it, press C to toggle its color.
+
+float sdCircle( in vec2 p, in float r )
+{
+    return length(p) - r;
+}
+
+void mainImage( out vec4 fragColor, in vec2 fragCoord )
+{
+    vec2 center = iMouse.z > 0.0 || iMouse.x > 0.0 ? iMouse.xy : 0.5 * iResolution.xy;
+    float radius = keyDown(32) ? 0.25 : 0.1;
+
+    vec2 p = (fragCoord - center) / iResolution.y;
+    float d = sdCircle(p, radius);
+
+    vec3 inside = keyToggled(67) ? vec3(0.2, 0.6, 1.0) : vec3(1.0, 0.4, 0.2);
+    vec3 col = d < 0.0 ? inside : vec3(0.1) + 0.05 * cos(120.0 * d);
+    col = mix(col, vec3(1.0), 1.0 - smoothstep(0.0, 0.005, abs(d)));
+
+    fragColor = vec4(col, 1.0);
+}
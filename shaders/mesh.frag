@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec3 fragPosition;
+layout(location = 1) in vec3 fragNormal;
+layout(location = 2) in vec2 fragUV;
+
+layout(location = 0) out vec4 outColor;
+
+// Lambert lighting on a checkerboard of the texture coordinates.
+void main() {
+    vec3 light = normalize(vec3(0.5, 1.0, 0.8));
+    float diffuse = max(dot(normalize(fragNormal), light), 0.0);
+
+    vec2 cell = floor(fragUV * 8.0);
+    float checker = mod(cell.x + cell.y, 2.0);
+    vec3 albedo = mix(vec3(0.9, 0.5, 0.2), vec3(0.95), checker);
+
+    outColor = vec4(albedo * (0.15 + 0.85 * diffuse), 1.0);
+}
@@ -0,0 +1,45 @@
+#version 450
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec2 inUV;
+
+layout(location = 0) out vec3 fragPosition;
+layout(location = 1) out vec3 fragNormal;
+layout(location = 2) out vec2 fragUV;
+
+layout(push_constant) uniform ShadertoyFrame {
+    vec3 iResolution;
+    float iTime;
+    int iFrame;
+};
+
+// Turns the model around the y axis in front of a camera 3 units away, looking down -z.
+void main() {
+    float angle = iTime * 0.5;
+    mat3 rotation = mat3(
+        cos(angle), 0.0, -sin(angle),
+        0.0, 1.0, 0.0,
+        sin(angle), 0.0, cos(angle)
+    );
+
+    vec3 position = rotation * inPosition;
+    vec3 view = position - vec3(0.0, 0.0, 3.0);
+
+    // Perspective with a 45 degree vertical field of view, mapping depth from 0.1 to 100 onto 0..1.
+    float f = 1.0 / tan(radians(45.0) * 0.5);
+    float aspect = iResolution.x / iResolution.y;
+    float near = 0.1;
+    float far = 100.0;
+
+    gl_Position = vec4(
+        view.x * f / aspect,
+        view.y * f,
+        view.z * far / (near - far) + far * near / (near - far),
+        -view.z
+    );
+
+    fragPosition = position;
+    fragNormal = rotation * inNormal;
+    fragUV = inUV;
+}
@@ -0,0 +1,15 @@
+// Sums a few octaves of the noise texture bound to iChannel0 into drifting clouds.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.y;
+
+    float value = 0.0;
+    float amplitude = 0.5;
+    for (int octave = 0; octave < 5; octave++) {
+        value += amplitude * texture(iChannel0, uv * 0.25 + iTime * 0.01).r;
+        uv *= 2.0;
+        amplitude *= 0.5;
+    }
+
+    vec3 sky = mix(vec3(0.2, 0.4, 0.8), vec3(1.0), smoothstep(0.4, 0.8, value));
+    fragColor = vec4(sky, 1.0);
+}
@@ -4,13 +4,21 @@ precision mediump float;
 
 layout(location = 0) out vec4 outColor;
 
-layout(binding = 0) uniform UniformBufferObject {
-    float time;
-
-    float width;
-    float height;
-    // vec2 resolution;
-} ubo;
+layout(push_constant) uniform ShadertoyFrame {
+    vec3 iResolution;
+    float iTime;
+    int iFrame;
+};
+
+layout(binding = 0) uniform ShadertoyInputs {
+    float iTimeDelta;
+    float iFrameRate;
+    uint iMouseButtons;
+    vec4 iMouse;
+    vec4 iDate;
+    vec2 iCursor;
+    uvec4 iKeyboard[6];
+};
 
 
 float dot2( in vec2 a, in vec2 b )
@@ -37,10 +45,10 @@ void main() {
     // vec2 pos = vec2(gl_FragCoord.x, -gl_FragCoord.y);
     vec2 pos = gl_FragCoord.xy;
 
-    vec2 uv = pos / vec2(ubo.width, ubo.height) * 2.0 - 1.0;
-    uv.x *= (ubo.width / ubo.height);
+    vec2 uv = pos / iResolution.xy * 2.0 - 1.0;
+    uv.x *= (iResolution.x / iResolution.y);
 
-    // vec3 col = 0.5 + 0.5*cos(ubo.time+uv.xyx+vec3(0,2,4));
+    // vec3 col = 0.5 + 0.5*cos(iTime+uv.xyx+vec3(0,2,4));
 
     // float distanceFromCenter = distance(uv, vec2(0.5));
     float d = sdHeart(uv);
@@ -1,13 +1,18 @@
 #version 450
 
-layout(binding = 0) uniform UniformBufferObject {
-    float time;
-    vec2 resolution;
-} ubo;
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec2 inUV;
 
-layout(location = 0) in vec2 inPosition;
-layout(location = 1) in vec3 inColor;
+// The same outputs as mesh.vert, so any fragment shader can be drawn on the quad or a mesh.
+layout(location = 0) out vec3 fragPosition;
+layout(location = 1) out vec3 fragNormal;
+layout(location = 2) out vec2 fragUV;
 
 void main() {
-    gl_Position = vec4(inPosition, 0.0, 1.0);
+    gl_Position = vec4(inPosition.xy, 0.0, 1.0);
+
+    fragPosition = inPosition;
+    fragNormal = inNormal;
+    fragUV = inUV;
 }
@@ -0,0 +1,11 @@
+// Fades the previous frame and draws a dot on top of it.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec3 previous = texture(iChannel0, uv).rgb * 0.96;
+
+    vec2 center = iResolution.xy * (0.5 + 0.3 * vec2(cos(iTime), sin(iTime * 1.3)));
+    float spot = smoothstep(12.0, 8.0, length(fragCoord - center));
+    vec3 color = 0.5 + 0.5 * cos(iTime + vec3(0.0, 2.0, 4.0));
+
+    fragColor = vec4(max(previous, color * spot), 1.0);
+}
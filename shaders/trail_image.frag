@@ -0,0 +1,7 @@
+// Shows Buffer A with a slight glow.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec3 color = texture(iChannel0, uv).rgb;
+
+    fragColor = vec4(pow(color, vec3(0.8)), 1.0);
+}
@@ -0,0 +1,646 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::buffers::common::get_memory_type_index;
+use crate::resources::*;
+
+/// The size of the `vk::DeviceMemory` blocks resources are sub-allocated from. Larger resources get a block of
+/// their own.
+pub const BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+/// Buffers and optimally tiled images are kept in separate blocks, so neighbouring resources never have to be
+/// padded to `bufferImageGranularity`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resource {
+    Buffer,
+    Image,
+}
+
+/// A range of a memory block bound to one buffer or image, see `MemoryAllocator::allocate`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+    block: usize,
+    /// Where the allocation is mapped if its memory is host visible, blocks stay mapped while they exist.
+    mapped: Option<NonNull<u8>>,
+}
+
+impl Allocation {
+    /// The host address of the allocation, only host visible memory is mapped.
+    pub fn mapped_ptr(&self) -> Result<*mut u8> {
+        self.mapped
+            .map(|p| p.as_ptr())
+            .ok_or_else(|| anyhow!("The allocation isn't host visible."))
+    }
+}
+
+/// A `vk::DeviceMemory` with the ranges that aren't allocated, sorted by offset and never adjacent.
+#[derive(Clone, Debug)]
+struct MemoryBlock {
+    memory: vk::DeviceMemory,
+    memory_type: u32,
+    resource: Resource,
+    size: vk::DeviceSize,
+    free: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+    allocations: usize,
+    mapped: Option<NonNull<u8>>,
+}
+
+impl MemoryBlock {
+    /// Takes the first free range that fits `size` bytes at `alignment`, returning its offset.
+    fn allocate(&mut self, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Option<vk::DeviceSize> {
+        let (index, offset) = self.free.iter().enumerate().find_map(|(index, (start, end))| {
+            let offset = align_up(*start, alignment);
+            (offset + size <= *end).then_some((index, offset))
+        })?;
+
+        let (start, end) = self.free.remove(index);
+        if offset + size < end {
+            self.free.insert(index, (offset + size, end));
+        }
+        if start < offset {
+            self.free.insert(index, (start, offset));
+        }
+
+        self.allocations += 1;
+
+        Some(offset)
+    }
+
+    /// Returns a range to the free list, merging it with the free ranges next to it.
+    fn free(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let index = self.free.partition_point(|(start, _)| *start < offset);
+        self.free.insert(index, (offset, offset + size));
+
+        if index + 1 < self.free.len() && self.free[index].1 == self.free[index + 1].0 {
+            self.free[index].1 = self.free.remove(index + 1).1;
+        }
+        if index > 0 && self.free[index - 1].1 == self.free[index].0 {
+            self.free[index - 1].1 = self.free.remove(index).1;
+        }
+
+        self.allocations -= 1;
+    }
+
+    fn free_bytes(&self) -> vk::DeviceSize {
+        self.free.iter().map(|(start, end)| end - start).sum()
+    }
+}
+
+/// Allocates large memory blocks per memory type and hands out aligned ranges of them, so the number of
+/// `vk::DeviceMemory` objects stays far below the driver's allocation limit.
+///
+/// Clones share the blocks, and every `Buffer` and `Image` created from the allocator holds a clone. A block is
+/// freed as soon as its last allocation is, except for one spare empty block of each kind, the rest when all
+/// clones are dropped.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryAllocator {
+    shared: Rc<SharedAllocator>,
+}
+
+#[derive(Debug, Default)]
+struct SharedAllocator {
+    device: Option<SharedDevice>,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    /// Indexed by `Allocation::block`, the slots of freed blocks are reused by new ones.
+    blocks: RefCell<Vec<Option<MemoryBlock>>>,
+}
+
+impl MemoryAllocator {
+    pub unsafe fn new(instance: &Instance, device: &SharedDevice, physical_device: vk::PhysicalDevice) -> Self {
+        let memory_properties = instance.get_physical_device_memory_properties(physical_device);
+
+        let shared = SharedAllocator { device: Some(device.clone()), memory_properties, blocks: RefCell::default() };
+
+        Self { shared: Rc::new(shared) }
+    }
+
+    fn device(&self) -> &Device {
+        self.shared.device.as_ref().expect("The memory allocator has no device.")
+    }
+
+    /// Finds room for a resource with the given requirements in memory with the given properties, creating a
+    /// block if none has enough space left.
+    pub unsafe fn allocate(
+        &self,
+        requirements: vk::MemoryRequirements,
+        properties: vk::MemoryPropertyFlags,
+        resource: Resource,
+    ) -> Result<Allocation> {
+        let memory_type = get_memory_type_index(&self.shared.memory_properties, properties, requirements)?;
+        let mut blocks = self.shared.blocks.borrow_mut();
+
+        let (block, offset) = match allocate_in_blocks(&mut blocks, memory_type, resource, requirements) {
+            Some(found) => found,
+            None => {
+                let size = requirements.size.max(BLOCK_SIZE);
+                let mut new_block = self.create_block(memory_type, resource, size)?;
+                let Some(offset) = new_block.allocate(requirements.size, requirements.alignment) else {
+                    destroy_block(self.device(), &new_block);
+                    return Err(anyhow!("A new memory block has no room for {} bytes.", requirements.size));
+                };
+
+                (insert_block(&mut blocks, new_block), offset)
+            }
+        };
+
+        let block_ref = blocks[block].as_ref().expect("The allocated block exists.");
+        let mapped = block_ref.mapped.map(|p| NonNull::new_unchecked(p.as_ptr().add(offset as usize)));
+
+        Ok(Allocation { memory: block_ref.memory, offset, size: requirements.size, block, mapped })
+    }
+
+    /// Creates a buffer bound to a new allocation.
+    pub unsafe fn create_buffer(
+        &self,
+        info: &vk::BufferCreateInfo,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Result<Buffer> {
+        let device = self.device();
+        let buffer = device.create_buffer(info, None)?;
+        let requirements = device.get_buffer_memory_requirements(buffer);
+
+        let allocation = match self.allocate(requirements, properties, Resource::Buffer) {
+            Ok(allocation) => allocation,
+            Err(error) => {
+                device.destroy_buffer(buffer, None);
+                return Err(error);
+            }
+        };
+
+        let buffer = Buffer { handle: buffer, allocation, allocator: Some(self.clone()) };
+        device.bind_buffer_memory(*buffer, allocation.memory, allocation.offset)?;
+
+        Ok(buffer)
+    }
+
+    /// Creates an image bound to a new allocation.
+    pub unsafe fn create_image(
+        &self,
+        info: &vk::ImageCreateInfo,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Result<Image> {
+        let device = self.device();
+        let image = device.create_image(info, None)?;
+        let requirements = device.get_image_memory_requirements(image);
+
+        let allocation = match self.allocate(requirements, properties, Resource::Image) {
+            Ok(allocation) => allocation,
+            Err(error) => {
+                device.destroy_image(image, None);
+                return Err(error);
+            }
+        };
+
+        let image = Image { handle: image, allocation, allocator: Some(self.clone()) };
+        device.bind_image_memory(*image, allocation.memory, allocation.offset)?;
+
+        Ok(image)
+    }
+
+    /// Returns the range of an allocation, the resource bound to it must have been destroyed. The block is freed
+    /// when it was its last allocation, unless it's the only empty block of its kind (see `release_block`).
+    pub fn free(&self, allocation: &Allocation) {
+        if allocation.memory.is_null() {
+            return;
+        }
+
+        let mut blocks = self.shared.blocks.borrow_mut();
+        if let Some(block) = release_block(&mut blocks, allocation) {
+            debug!("Freeing an empty {} byte memory block of type {}", block.size, block.memory_type);
+            unsafe { destroy_block(self.device(), &block) };
+        }
+    }
+
+    pub fn stats(&self) -> AllocatorStats {
+        let blocks = self.shared.blocks.borrow();
+        let mut stats = AllocatorStats::default();
+
+        for block in blocks.iter().flatten() {
+            stats.blocks += 1;
+            stats.allocations += block.allocations;
+            stats.reserved_bytes += block.size;
+            stats.used_bytes += block.size - block.free_bytes();
+            stats.free_ranges += block.free.len();
+            stats.largest_free_range = block.free
+                .iter()
+                .map(|(start, end)| end - start)
+                .fold(stats.largest_free_range, vk::DeviceSize::max);
+        }
+
+        stats
+    }
+
+    unsafe fn create_block(
+        &self,
+        memory_type: u32,
+        resource: Resource,
+        size: vk::DeviceSize,
+    ) -> Result<MemoryBlock> {
+        debug!("Allocating a {} byte memory block of type {} for {:?}s", size, memory_type, resource);
+
+        let info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(memory_type);
+
+        let device = self.device();
+        let memory = device.allocate_memory(&info, None)?;
+
+        let property_flags = self.shared.memory_properties.memory_types[memory_type as usize].property_flags;
+        let mapped = if property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            match device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty()) {
+                Ok(pointer) => NonNull::new(pointer.cast()),
+                Err(error) => {
+                    device.free_memory(memory, None);
+                    return Err(anyhow!(error));
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(MemoryBlock {
+            memory,
+            memory_type,
+            resource,
+            size,
+            free: vec![(0, size)],
+            allocations: 0,
+            mapped,
+        })
+    }
+}
+
+impl Drop for SharedAllocator {
+    /// Frees all blocks, reporting allocations that were never freed.
+    fn drop(&mut self) {
+        let Some(device) = &self.device else {
+            return;
+        };
+
+        let blocks = self.blocks.get_mut();
+        let allocations = blocks.iter().flatten().map(|b| b.allocations).sum::<usize>();
+        if allocations > 0 {
+            warn!("Destroying the memory allocator with {} live allocations.", allocations);
+        }
+
+        for block in blocks.drain(..).flatten() {
+            unsafe { destroy_block(device, &block) };
+        }
+    }
+}
+
+/// Allocates from the first block of the memory type and resource kind with room for the requirements, returning
+/// the block index and offset.
+fn allocate_in_blocks(
+    blocks: &mut [Option<MemoryBlock>],
+    memory_type: u32,
+    resource: Resource,
+    requirements: vk::MemoryRequirements,
+) -> Option<(usize, vk::DeviceSize)> {
+    blocks.iter_mut().enumerate().find_map(|(index, block)| {
+        let block = block.as_mut()?;
+        if block.memory_type != memory_type || block.resource != resource {
+            return None;
+        }
+
+        block.allocate(requirements.size, requirements.alignment).map(|offset| (index, offset))
+    })
+}
+
+/// Puts a new block into the first free slot, returning its index.
+fn insert_block(blocks: &mut Vec<Option<MemoryBlock>>, block: MemoryBlock) -> usize {
+    let index = match blocks.iter().position(Option::is_none) {
+        Some(index) => index,
+        None => {
+            blocks.push(None);
+            blocks.len() - 1
+        }
+    };
+    blocks[index] = Some(block);
+
+    index
+}
+
+/// Returns an allocation's range to its block and takes the block out if it's empty and should be destroyed.
+///
+/// One empty `BLOCK_SIZE` block is kept per memory type and resource kind, so resources recreated together, like
+/// the render targets on every resize, don't free and allocate a whole block each time.
+fn release_block(blocks: &mut [Option<MemoryBlock>], allocation: &Allocation) -> Option<MemoryBlock> {
+    let block = blocks[allocation.block].as_mut().expect("The allocation's block was already freed.");
+    block.free(allocation.offset, allocation.size);
+
+    if block.allocations > 0 {
+        return None;
+    }
+
+    let (memory_type, resource, size) = (block.memory_type, block.resource, block.size);
+    let spare_exists = blocks.iter().enumerate().any(|(index, other)| {
+        other.as_ref().is_some_and(|other| {
+            index != allocation.block
+                && other.allocations == 0
+                && other.memory_type == memory_type
+                && other.resource == resource
+        })
+    });
+
+    if size > BLOCK_SIZE || spare_exists {
+        blocks[allocation.block].take()
+    } else {
+        None
+    }
+}
+
+/// Unmaps and frees the memory of a block, no resource may be bound to it anymore.
+unsafe fn destroy_block(device: &Device, block: &MemoryBlock) {
+    if block.mapped.is_some() {
+        device.unmap_memory(block.memory);
+    }
+    device.free_memory(block.memory, None);
+}
+
+/// A buffer bound to memory from a `MemoryAllocator`, destroyed and freed when dropped. It dereferences to the
+/// buffer handle.
+#[derive(Debug, Default)]
+pub struct Buffer {
+    handle: vk::Buffer,
+    allocation: Allocation,
+    allocator: Option<MemoryAllocator>,
+}
+
+impl Buffer {
+    pub fn allocation(&self) -> &Allocation {
+        &self.allocation
+    }
+}
+
+impl Deref for Buffer {
+    type Target = vk::Buffer;
+
+    fn deref(&self) -> &vk::Buffer {
+        &self.handle
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if let Some(allocator) = &self.allocator {
+            unsafe { allocator.device().destroy_buffer(self.handle, None) };
+            allocator.free(&self.allocation);
+        }
+    }
+}
+
+/// An image bound to memory from a `MemoryAllocator`, destroyed and freed when dropped. It dereferences to the
+/// image handle.
+#[derive(Debug, Default)]
+pub struct Image {
+    handle: vk::Image,
+    allocation: Allocation,
+    allocator: Option<MemoryAllocator>,
+}
+
+impl Image {
+    pub fn allocation(&self) -> &Allocation {
+        &self.allocation
+    }
+}
+
+impl Deref for Image {
+    type Target = vk::Image;
+
+    fn deref(&self) -> &vk::Image {
+        &self.handle
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        if let Some(allocator) = &self.allocator {
+            unsafe { allocator.device().destroy_image(self.handle, None) };
+            allocator.free(&self.allocation);
+        }
+    }
+}
+
+/// How much of the allocator's memory is in use and how scattered the rest is.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AllocatorStats {
+    pub blocks: usize,
+    pub allocations: usize,
+    pub reserved_bytes: vk::DeviceSize,
+    pub used_bytes: vk::DeviceSize,
+    pub free_ranges: usize,
+    pub largest_free_range: vk::DeviceSize,
+}
+
+impl AllocatorStats {
+    /// 0 when the free memory is one contiguous range, approaching 1 the more it is split up.
+    pub fn fragmentation(&self) -> f32 {
+        let free = self.reserved_bytes - self.used_bytes;
+        if free == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free_range as f32 / free as f32
+        }
+    }
+}
+
+impl fmt::Display for AllocatorStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} allocations using {} of {} KiB in {} blocks, {} free ranges, {:.0}% fragmented",
+            self.allocations,
+            self.used_bytes / 1024,
+            self.reserved_bytes / 1024,
+            self.blocks,
+            self.free_ranges,
+            self.fragmentation() * 100.0,
+        )
+    }
+}
+
+fn align_up(offset: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    if alignment <= 1 {
+        offset
+    } else {
+        offset.div_ceil(alignment) * alignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A block without memory behind it, only its free list is used.
+    fn block(size: vk::DeviceSize) -> MemoryBlock {
+        typed_block(size, 0, Resource::Buffer)
+    }
+
+    fn typed_block(size: vk::DeviceSize, memory_type: u32, resource: Resource) -> MemoryBlock {
+        MemoryBlock {
+            memory: vk::DeviceMemory::null(),
+            memory_type,
+            resource,
+            size,
+            free: vec![(0, size)],
+            allocations: 0,
+            mapped: None,
+        }
+    }
+
+    #[test]
+    fn align_up_rounds_to_the_next_multiple() {
+        assert_eq!(align_up(0, 256), 0);
+        assert_eq!(align_up(1, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(257, 256), 512);
+        assert_eq!(align_up(13, 1), 13);
+        assert_eq!(align_up(13, 0), 13);
+    }
+
+    #[test]
+    fn allocate_splits_the_free_range() {
+        let mut block = block(1024);
+
+        assert_eq!(block.allocate(100, 1), Some(0));
+        assert_eq!(block.allocate(100, 1), Some(100));
+        assert_eq!(block.free, vec![(200, 1024)]);
+        assert_eq!(block.allocations, 2);
+    }
+
+    #[test]
+    fn allocate_keeps_the_padding_before_an_aligned_offset() {
+        let mut block = block(1024);
+
+        assert_eq!(block.allocate(10, 1), Some(0));
+        assert_eq!(block.allocate(100, 256), Some(256));
+        assert_eq!(block.free, vec![(10, 256), (356, 1024)]);
+
+        // The padding is used by allocations that fit into it.
+        assert_eq!(block.allocate(200, 16), Some(16));
+        assert_eq!(block.free, vec![(10, 16), (216, 256), (356, 1024)]);
+    }
+
+    #[test]
+    fn allocate_takes_an_exact_fit_whole() {
+        let mut block = block(256);
+
+        assert_eq!(block.allocate(256, 256), Some(0));
+        assert!(block.free.is_empty());
+    }
+
+    #[test]
+    fn allocate_fails_when_nothing_fits() {
+        let mut block = block(1024);
+
+        assert_eq!(block.allocate(2048, 1), None);
+        assert_eq!(block.allocate(1000, 1), Some(0));
+        // 24 bytes are left, but not at this alignment.
+        assert_eq!(block.allocate(8, 64), None);
+        assert_eq!(block.free, vec![(1000, 1024)]);
+        assert_eq!(block.allocations, 1);
+    }
+
+    #[test]
+    fn free_merges_adjacent_ranges() {
+        let mut block = block(300);
+        let offsets = [block.allocate(100, 1), block.allocate(100, 1), block.allocate(100, 1)].map(Option::unwrap);
+
+        block.free(offsets[0], 100);
+        block.free(offsets[2], 100);
+        assert_eq!(block.free, vec![(0, 100), (200, 300)]);
+
+        // Freeing the middle range joins both neighbours.
+        block.free(offsets[1], 100);
+        assert_eq!(block.free, vec![(0, 300)]);
+        assert_eq!(block.allocations, 0);
+        assert_eq!(block.free_bytes(), 300);
+    }
+
+    #[test]
+    fn free_merges_with_the_padding_before_an_allocation() {
+        let mut block = block(1024);
+        assert_eq!(block.allocate(10, 1), Some(0));
+        assert_eq!(block.allocate(100, 256), Some(256));
+
+        block.free(256, 100);
+        assert_eq!(block.free, vec![(10, 1024)]);
+        assert_eq!(block.allocations, 1);
+    }
+
+    fn requirements(size: vk::DeviceSize) -> vk::MemoryRequirements {
+        vk::MemoryRequirements { size, alignment: 256, memory_type_bits: !0 }
+    }
+
+    fn allocate(blocks: &mut [Option<MemoryBlock>], size: vk::DeviceSize) -> Allocation {
+        let (block, offset) = allocate_in_blocks(blocks, 0, Resource::Image, requirements(size)).unwrap();
+        Allocation { offset, size, block, ..Default::default() }
+    }
+
+    #[test]
+    fn allocations_only_use_blocks_of_their_kind() {
+        let mut blocks = vec![
+            Some(typed_block(BLOCK_SIZE, 1, Resource::Image)),
+            Some(typed_block(BLOCK_SIZE, 0, Resource::Buffer)),
+            Some(typed_block(BLOCK_SIZE, 0, Resource::Image)),
+        ];
+
+        assert_eq!(allocate_in_blocks(&mut blocks, 0, Resource::Image, requirements(1024)), Some((2, 0)));
+        assert_eq!(allocate_in_blocks(&mut blocks, 0, Resource::Image, requirements(1024)), Some((2, 1024)));
+        assert_eq!(allocate_in_blocks(&mut blocks, 2, Resource::Image, requirements(1024)), None);
+        assert_eq!(allocate_in_blocks(&mut blocks, 0, Resource::Image, requirements(BLOCK_SIZE)), None);
+    }
+
+    #[test]
+    fn the_last_empty_block_is_kept_and_reused() {
+        let mut blocks = Vec::new();
+        insert_block(&mut blocks, typed_block(BLOCK_SIZE, 0, Resource::Image));
+
+        let allocation = allocate(&mut blocks, 1024);
+        assert!(release_block(&mut blocks, &allocation).is_none());
+        assert_eq!(blocks[0].as_ref().unwrap().allocations, 0);
+
+        // A resize recreating the same resources allocates from the kept block again.
+        let allocation = allocate(&mut blocks, 2048);
+        assert_eq!((allocation.block, allocation.offset), (0, 0));
+    }
+
+    #[test]
+    fn empty_blocks_beyond_the_spare_are_freed() {
+        let mut blocks = Vec::new();
+        insert_block(&mut blocks, typed_block(BLOCK_SIZE, 0, Resource::Image));
+        insert_block(&mut blocks, typed_block(BLOCK_SIZE, 0, Resource::Image));
+
+        let first = allocate(&mut blocks, BLOCK_SIZE);
+        let second = allocate(&mut blocks, BLOCK_SIZE);
+        assert_eq!((first.block, second.block), (0, 1));
+
+        assert!(release_block(&mut blocks, &first).is_none());
+        assert!(release_block(&mut blocks, &second).is_some());
+        assert!(blocks[1].is_none());
+
+        // The freed slot is reused by the next block.
+        assert_eq!(insert_block(&mut blocks, typed_block(BLOCK_SIZE, 1, Resource::Buffer)), 1);
+    }
+
+    #[test]
+    fn empty_dedicated_blocks_are_freed() {
+        let mut blocks = Vec::new();
+        insert_block(&mut blocks, typed_block(2 * BLOCK_SIZE, 0, Resource::Image));
+
+        let allocation = allocate(&mut blocks, 2 * BLOCK_SIZE);
+        assert!(release_block(&mut blocks, &allocation).is_some());
+        assert!(blocks[0].is_none());
+    }
+}
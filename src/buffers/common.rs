@@ -2,45 +2,45 @@ use vulkanalia::prelude::v1_0::*;
 use anyhow::Result;
 use anyhow::anyhow;
 
+use crate::allocator::*;
+
 pub unsafe fn create_buffer(
-    instance: &Instance,
-    device: &Device,
-    physical_device: &vk::PhysicalDevice,
+    allocator: &MemoryAllocator,
     size: vk::DeviceSize,
     usage: vk::BufferUsageFlags,
     properties: vk::MemoryPropertyFlags,
-) -> Result<(vk::Buffer, vk::DeviceMemory)> {
-    // Buffer
+) -> Result<Buffer> {
+    create_shared_buffer(allocator, size, usage, properties, &[])
+}
 
-    let buffer_info = vk::BufferCreateInfo::builder()
+/// Like `create_buffer`, but used concurrently by the given queue families. With fewer than two families the
+/// buffer is exclusive to the queue family using it first.
+pub unsafe fn create_shared_buffer(
+    allocator: &MemoryAllocator,
+    size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+    properties: vk::MemoryPropertyFlags,
+    queue_families: &[u32],
+) -> Result<Buffer> {
+    let mut buffer_info = vk::BufferCreateInfo::builder()
         .size(size)
         .usage(usage)
         .sharing_mode(vk::SharingMode::EXCLUSIVE);
 
-    let buffer = device.create_buffer(&buffer_info, None)?;
-
-    // Memory
+    if queue_families.len() > 1 {
+        buffer_info = buffer_info
+            .sharing_mode(vk::SharingMode::CONCURRENT)
+            .queue_family_indices(queue_families);
+    }
 
-    let requirements = device.get_buffer_memory_requirements(buffer);
-
-    let memory_info = vk::MemoryAllocateInfo::builder()
-        .allocation_size(requirements.size)
-        .memory_type_index(get_memory_type_index(instance, physical_device, properties, requirements)?);
-
-    let buffer_memory = device.allocate_memory(&memory_info, None)?;
-
-    device.bind_buffer_memory(buffer, buffer_memory, 0)?;
-
-    Ok((buffer, buffer_memory))
+    allocator.create_buffer(&buffer_info, properties)
 }
 
-pub unsafe fn get_memory_type_index(
-    instance: &Instance,
-    physical_device: &vk::PhysicalDevice,
+pub fn get_memory_type_index(
+    memory: &vk::PhysicalDeviceMemoryProperties,
     properties: vk::MemoryPropertyFlags,
     requirements: vk::MemoryRequirements,
 ) -> Result<u32> {
-    let memory = instance.get_physical_device_memory_properties(*physical_device);
     (0..memory.memory_type_count)
         .find(|i| {
             let suitable = (requirements.memory_type_bits & (1 << i)) != 0;
@@ -48,4 +48,4 @@ pub unsafe fn get_memory_type_index(
             suitable && memory_type.property_flags.contains(properties)
         })
         .ok_or_else(|| anyhow!("Failed to find suitable memory type."))
-}
\ No newline at end of file
+}
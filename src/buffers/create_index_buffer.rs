@@ -0,0 +1,14 @@
+use vulkanalia::prelude::v1_0::*;
+use anyhow::Result;
+
+use crate::allocator::*;
+use crate::buffers::upload::*;
+
+pub unsafe fn create_index_buffer(
+    device: &Device,
+    allocator: &MemoryAllocator,
+    transfer: &TransferQueue,
+    indices: &[u32],
+) -> Result<Buffer> {
+    upload_buffer(device, allocator, transfer, indices, vk::BufferUsageFlags::INDEX_BUFFER)
+}
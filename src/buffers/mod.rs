@@ -1,2 +1,4 @@
 pub mod create_vertex_buffer;
+pub mod create_index_buffer;
+pub mod upload;
 pub mod common;
\ No newline at end of file
@@ -0,0 +1,93 @@
+use log::debug;
+use vulkanalia::prelude::v1_0::*;
+use anyhow::Result;
+use std::ptr::copy_nonoverlapping as memcpy;
+use std::mem::size_of_val;
+
+use crate::allocator::*;
+use crate::buffers::common::*;
+use crate::resources::*;
+use crate::single_time_commands::*;
+
+/// The queue uploads are submitted to, a dedicated transfer queue if the device has one.
+#[derive(Debug, Default)]
+pub struct TransferQueue {
+    pub queue: vk::Queue,
+    pub queue_family: u32,
+    /// The family of the graphics queue reading the uploaded buffers.
+    pub graphics_queue_family: u32,
+    /// A transient pool for the one-shot upload command buffers.
+    pub command_pool: CommandPool,
+}
+
+impl TransferQueue {
+    pub unsafe fn create(device: &SharedDevice, queue_family: u32, graphics_queue_family: u32) -> Result<Self> {
+        let info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(queue_family);
+
+        let command_pool = Owned::new(device, device.create_command_pool(&info, None)?);
+        let queue = device.get_device_queue(queue_family, 0);
+
+        Ok(Self { queue, queue_family, graphics_queue_family, command_pool })
+    }
+
+    /// The families sharing uploaded buffers, both when transfers run on their own family.
+    fn queue_families(&self) -> Vec<u32> {
+        if self.queue_family == self.graphics_queue_family {
+            vec![self.queue_family]
+        } else {
+            vec![self.queue_family, self.graphics_queue_family]
+        }
+    }
+}
+
+/// Copies `data` into a new `DEVICE_LOCAL` buffer with the given usage, through a host visible staging buffer.
+/// Waits for the copy to finish, the staging buffer is freed before returning.
+pub unsafe fn upload_buffer<T: Copy>(
+    device: &Device,
+    allocator: &MemoryAllocator,
+    transfer: &TransferQueue,
+    data: &[T],
+    usage: vk::BufferUsageFlags,
+) -> Result<Buffer> {
+    let size = size_of_val(data) as vk::DeviceSize;
+
+    debug!("Uploading {} bytes for {:?}", size, usage);
+
+    let staging_buffer = create_buffer(
+        allocator,
+        size,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+
+    memcpy(data.as_ptr(), staging_buffer.allocation().mapped_ptr()?.cast(), data.len());
+
+    let buffer = create_shared_buffer(
+        allocator,
+        size,
+        usage | vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        &transfer.queue_families(),
+    )?;
+
+    copy_buffer(device, transfer, *staging_buffer, *buffer, size)?;
+
+    Ok(buffer)
+}
+
+unsafe fn copy_buffer(
+    device: &Device,
+    transfer: &TransferQueue,
+    source: vk::Buffer,
+    destination: vk::Buffer,
+    size: vk::DeviceSize,
+) -> Result<()> {
+    let command_buffer = begin_single_time_commands(device, *transfer.command_pool)?;
+
+    let regions = vk::BufferCopy::builder().size(size);
+    device.cmd_copy_buffer(command_buffer, source, destination, &[regions]);
+
+    end_single_time_commands(device, *transfer.command_pool, transfer.queue, command_buffer)
+}
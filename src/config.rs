@@ -0,0 +1,395 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::create_color_objects::msaa_samples_from_count;
+use crate::headless::parse_extent;
+use crate::shader_manager::*;
+
+/// The config file read from the working dir when no `--config` is given.
+pub const CONFIG_FILE: &str = "engine.conf";
+
+pub const DEFAULT_TITLE: &str = "Vulkan Tutorial (Rust)";
+
+/// The most frames allowed in flight, more only adds latency.
+const MAX_FRAMES_IN_FLIGHT: usize = 8;
+
+/// The settings of the config file, each also a `--<key>` flag with dashes for underscores.
+const KEYS: &[&str] = &[
+    "title",
+    "size",
+    "frames_in_flight",
+    "validation",
+    "present_mode",
+    "shader_dir",
+    "shader",
+    "scene",
+    "msaa",
+];
+
+/// How the engine window is set up and what it renders.
+///
+/// Every setting is read from the config file first and can then be overridden on the command line, see `load`.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub title: String,
+    /// The initial size of the window's drawable area in logical pixels.
+    pub size: vk::Extent2D,
+    /// How many frames the CPU may record ahead of the GPU.
+    pub frames_in_flight: usize,
+    /// Enables the Khronos validation layer and its debug messages, on in debug builds by default.
+    pub validation: bool,
+    /// The preferred present mode, FIFO is used when the surface doesn't support it.
+    pub present_mode: vk::PresentModeKHR,
+    /// The shaders dir, `SHADER_DIR` or `./shaders/` if not set.
+    pub shader_dir: Option<PathBuf>,
+    /// The fragment shader to draw, `shader.frag` if neither a shader nor a scene is set.
+    pub shader: Option<String>,
+    /// The scene to render instead of a single fragment shader.
+    pub scene: Option<String>,
+    pub msaa_samples: vk::SampleCountFlags,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            title: DEFAULT_TITLE.to_string(),
+            size: vk::Extent2D { width: 1024, height: 768 },
+            frames_in_flight: 2,
+            validation: cfg!(debug_assertions),
+            present_mode: vk::PresentModeKHR::MAILBOX,
+            shader_dir: None,
+            shader: None,
+            scene: None,
+            msaa_samples: vk::SampleCountFlags::_1,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file given as `--config <file>`, or `engine.conf` in the working dir if it exists, and
+    /// applies the command line flags on top.
+    ///
+    /// Every setting has a flag named like its key with dashes, `frames_in_flight = 3` in the file is
+    /// `--frames-in-flight 3` on the command line. `--validation` and `--no-validation` take no value.
+    pub fn load(args: impl Iterator<Item = String>) -> Result<Self> {
+        let args = args.skip(1).collect::<Vec<_>>();
+        let mut config = Self::default();
+
+        let path = match args.iter().position(|a| a == "--config") {
+            Some(index) => {
+                let path = args.get(index + 1).ok_or_else(|| anyhow!("Missing value for '--config'."))?;
+                Some(PathBuf::from(path))
+            }
+            None => Some(PathBuf::from(CONFIG_FILE)).filter(|p| p.exists()),
+        };
+
+        if let Some(path) = path {
+            let source = fs::read_to_string(&path).map_err(|e| anyhow!("Failed to read config {:?}: {}", path, e))?;
+            config.parse(&source).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            info!("Loaded config {:?}", path);
+        }
+
+        config.apply_args(&args)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Applies `<key> = <value>` lines, empty lines and lines starting with `#` are ignored.
+    pub fn parse(&mut self, source: &str) -> Result<()> {
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `<key> = <value>`", index + 1))?;
+
+            self.set(key.trim(), value.trim()).map_err(|e| anyhow!("line {}: {}", index + 1, e))?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_args(&mut self, args: &[String]) -> Result<()> {
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--validation" => self.validation = true,
+                "--no-validation" => self.validation = false,
+                // Read before the file.
+                "--config" => {
+                    args.next();
+                }
+                // Read by `HeadlessOptions` and `GoldenOptions`.
+                "--headless" | "--golden" | "--time" | "--tolerance" => {
+                    args.next();
+                }
+                "--bless" => {}
+                _ => {
+                    let key = arg
+                        .strip_prefix("--")
+                        .ok_or_else(|| anyhow!("Unexpected argument '{}'.", arg))?
+                        .replace('-', "_");
+                    let value = args.next().ok_or_else(|| anyhow!("Missing value for '{}'.", arg))?;
+
+                    self.set(&key, value).map_err(|e| anyhow!("{}: {}", arg, e))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "title" => self.title = value.to_string(),
+            "size" => self.size = parse_extent(value)?,
+            "frames_in_flight" => {
+                self.frames_in_flight = value.parse().map_err(|_| anyhow!("expected a number, got '{}'", value))?;
+            }
+            "validation" => {
+                self.validation = match value {
+                    "on" | "true" => true,
+                    "off" | "false" => false,
+                    _ => return Err(anyhow!("expected `on` or `off`, got '{}'", value)),
+                };
+            }
+            "present_mode" => {
+                self.present_mode = match value {
+                    "fifo" => vk::PresentModeKHR::FIFO,
+                    "fifo_relaxed" => vk::PresentModeKHR::FIFO_RELAXED,
+                    "mailbox" => vk::PresentModeKHR::MAILBOX,
+                    "immediate" => vk::PresentModeKHR::IMMEDIATE,
+                    _ => return Err(anyhow!("expected `fifo`, `fifo_relaxed`, `mailbox` or `immediate`, got '{}'", value)),
+                };
+            }
+            "shader_dir" => self.shader_dir = Some(PathBuf::from(value)),
+            "shader" => self.shader = Some(value.to_string()),
+            "scene" => self.scene = Some(value.to_string()),
+            "msaa" => {
+                let count = value.parse().map_err(|_| anyhow!("expected a number of samples, got '{}'", value))?;
+                self.msaa_samples = msaa_samples_from_count(count)?;
+            }
+            _ => return Err(anyhow!("unknown setting `{}`", key)),
+        }
+
+        Ok(())
+    }
+
+    /// Checks the settings that can be checked without a device.
+    fn validate(&self) -> Result<()> {
+        if self.size.width == 0 || self.size.height == 0 {
+            return Err(anyhow!("The window size must not be empty, got {}x{}.", self.size.width, self.size.height));
+        }
+
+        if !(1..=MAX_FRAMES_IN_FLIGHT).contains(&self.frames_in_flight) {
+            return Err(anyhow!(
+                "frames_in_flight must be between 1 and {}, got {}.",
+                MAX_FRAMES_IN_FLIGHT,
+                self.frames_in_flight,
+            ));
+        }
+
+        if self.shader.is_some() && self.scene.is_some() {
+            return Err(anyhow!("Set either a shader or a scene, not both."));
+        }
+
+        let shader_dir = self.shader_dir()?;
+        if !shader_dir.is_dir() {
+            return Err(anyhow!("The shaders dir {:?} doesn't exist.", shader_dir));
+        }
+
+        for file in self.shader.iter().chain(&self.scene) {
+            if !shader_dir.join(file).is_file() {
+                return Err(anyhow!("{:?} isn't a file in the shaders dir {:?}.", file, shader_dir));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The configured shaders dir, or the default one.
+    pub fn shader_dir(&self) -> Result<PathBuf> {
+        match &self.shader_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => find_shaders_path(),
+        }
+    }
+
+    /// A shader manager for the configured shaders dir drawing the configured shader or scene.
+    pub fn shader_manager(&self) -> Result<ShaderManager> {
+        let mut shader_manager = ShaderManager::create_in(&self.shader_dir()?)?;
+
+        if let Some(shader) = &self.shader {
+            shader_manager = shader_manager.with_fragment_shader(shader);
+        }
+        if let Some(scene) = &self.scene {
+            shader_manager = shader_manager.with_scene(scene);
+        }
+
+        Ok(shader_manager)
+    }
+}
+
+/// How many values follow `arg` if it's a flag read by `Config::load`, `None` if it isn't one.
+pub(crate) fn config_flag_values(arg: &str) -> Option<usize> {
+    match arg {
+        "--validation" | "--no-validation" => Some(0),
+        "--config" => Some(1),
+        _ => {
+            let key = arg.strip_prefix("--")?.replace('-', "_");
+            KEYS.contains(&key.as_str()).then_some(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    /// A config reading the shaders bundled with the repo, which passes `validate`.
+    fn bundled() -> Config {
+        Config {
+            shader_dir: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders").into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_sets_every_key() {
+        let mut config = Config::default();
+        config
+            .parse(
+                "# A comment\n\
+                 \n\
+                 title = Clouds\n\
+                 size = 640x480\n\
+                 frames_in_flight = 3\n\
+                 validation = off\n\
+                 present_mode = fifo_relaxed\n\
+                 shader_dir = /tmp/shaders\n\
+                 shader = noise.frag\n\
+                 scene = noise.scene\n\
+                 msaa = 4\n",
+            )
+            .unwrap();
+
+        assert_eq!(config.title, "Clouds");
+        assert_eq!(config.size, vk::Extent2D { width: 640, height: 480 });
+        assert_eq!(config.frames_in_flight, 3);
+        assert!(!config.validation);
+        assert_eq!(config.present_mode, vk::PresentModeKHR::FIFO_RELAXED);
+        assert_eq!(config.shader_dir, Some(PathBuf::from("/tmp/shaders")));
+        assert_eq!(config.shader.as_deref(), Some("noise.frag"));
+        assert_eq!(config.scene.as_deref(), Some("noise.scene"));
+        assert_eq!(config.msaa_samples, vk::SampleCountFlags::_4);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys() {
+        let error = Config::default().parse("title = A\nvsync = on\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unknown setting `vsync`");
+    }
+
+    #[test]
+    fn parse_rejects_bad_values() {
+        for source in [
+            "size = 640",
+            "frames_in_flight = two",
+            "validation = maybe",
+            "present_mode = vsync",
+            "msaa = 3",
+            "title",
+        ] {
+            let error = Config::default().parse(source).unwrap_err();
+            assert!(error.to_string().starts_with("line 1: "), "{}: {}", source, error);
+        }
+    }
+
+    #[test]
+    fn args_override_the_file() {
+        let mut config = Config::default();
+        config.parse("title = File\nmsaa = 2\nvalidation = on\n").unwrap();
+        config
+            .apply_args(&args(&["--title", "Flag", "--frames-in-flight", "4", "--no-validation"]))
+            .unwrap();
+
+        assert_eq!(config.title, "Flag");
+        assert_eq!(config.frames_in_flight, 4);
+        assert!(!config.validation);
+        assert_eq!(config.msaa_samples, vk::SampleCountFlags::_2);
+    }
+
+    #[test]
+    fn args_skip_the_config_and_mode_flags() {
+        let mut config = Config::default();
+        config
+            .apply_args(&args(&["--config", "other.conf", "--headless", "out.png", "--time", "2", "--bless"]))
+            .unwrap();
+
+        assert_eq!(config.title, DEFAULT_TITLE);
+    }
+
+    #[test]
+    fn args_reject_unknown_flags_and_missing_values() {
+        let mut config = Config::default();
+
+        let error = config.apply_args(&args(&["--sise", "64x64"])).unwrap_err();
+        assert_eq!(error.to_string(), "--sise: unknown setting `sise`");
+
+        let error = config.apply_args(&args(&["--size"])).unwrap_err();
+        assert_eq!(error.to_string(), "Missing value for '--size'.");
+
+        let error = config.apply_args(&args(&["shader.frag"])).unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected argument 'shader.frag'.");
+    }
+
+    #[test]
+    fn validate_accepts_the_bundled_shaders() {
+        let mut config = bundled();
+        config.scene = Some("mesh.scene".to_string());
+
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn validate_rejects_inconsistent_settings() {
+        let cases: [fn(&mut Config); 6] = [
+            |c| c.size = vk::Extent2D { width: 0, height: 768 },
+            |c| c.frames_in_flight = 0,
+            |c| c.frames_in_flight = MAX_FRAMES_IN_FLIGHT + 1,
+            |c| {
+                c.shader = Some("shader.frag".to_string());
+                c.scene = Some("mesh.scene".to_string());
+            },
+            |c| c.shader = Some("missing.frag".to_string()),
+            |c| c.shader_dir = Some(PathBuf::from("/nonexistent/shaders")),
+        ];
+
+        for (index, case) in cases.iter().enumerate() {
+            let mut config = bundled();
+            case(&mut config);
+            assert!(config.validate().is_err(), "case {} passed", index);
+        }
+    }
+
+    #[test]
+    fn config_flags_take_their_values() {
+        assert_eq!(config_flag_values("--no-validation"), Some(0));
+        assert_eq!(config_flag_values("--config"), Some(1));
+        assert_eq!(config_flag_values("--frames-in-flight"), Some(1));
+        assert_eq!(config_flag_values("--sise"), None);
+        assert_eq!(config_flag_values("size"), None);
+    }
+}
@@ -0,0 +1,97 @@
+use vulkanalia::prelude::v1_0::*;
+use anyhow::{anyhow, Result};
+use log::*;
+
+use crate::allocator::*;
+use crate::create_image::*;
+use crate::resources::*;
+
+/// The highest sample count the device supports for both color and depth attachments.
+pub unsafe fn get_max_msaa_samples(instance: &Instance, physical_device: &vk::PhysicalDevice) -> vk::SampleCountFlags {
+    let limits = instance.get_physical_device_properties(*physical_device).limits;
+    let counts = limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+
+    let samples = [
+        vk::SampleCountFlags::_64,
+        vk::SampleCountFlags::_32,
+        vk::SampleCountFlags::_16,
+        vk::SampleCountFlags::_8,
+        vk::SampleCountFlags::_4,
+        vk::SampleCountFlags::_2,
+    ]
+    .iter()
+    .cloned()
+    .find(|c| counts.contains(*c))
+    .unwrap_or(vk::SampleCountFlags::_1);
+
+    debug!("Max MSAA samples: {:?}", samples);
+
+    samples
+}
+
+/// The sample count flag for a number of samples, which must be a power of two.
+pub fn msaa_samples_from_count(count: u32) -> Result<vk::SampleCountFlags> {
+    vk::SampleCountFlags::from_bits(count)
+        .filter(|_| count.is_power_of_two())
+        .ok_or_else(|| anyhow!("{} isn't a valid number of MSAA samples, expected 1, 2, 4, 8, 16, 32 or 64.", count))
+}
+
+/// The sample count after `samples` when cycling through all counts up to `max`, wrapping around to 1.
+pub fn next_msaa_samples(samples: vk::SampleCountFlags, max: vk::SampleCountFlags) -> vk::SampleCountFlags {
+    let next = samples.bits() * 2;
+    if next > max.bits() {
+        vk::SampleCountFlags::_1
+    } else {
+        vk::SampleCountFlags::from_bits_truncate(next)
+    }
+}
+
+/// Creates the multisampled color image the swapchain images are resolved from, it has to be recreated with the
+/// swapchain. Its contents are only needed within the render pass.
+pub unsafe fn create_color_objects(
+    device: &SharedDevice,
+    allocator: &MemoryAllocator,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    samples: vk::SampleCountFlags,
+) -> Result<(Image, ImageView)> {
+    let image = create_image(
+        allocator,
+        extent,
+        1,
+        samples,
+        format,
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+
+    let view = create_image_view(device, *image, format, vk::ImageAspectFlags::COLOR, 1)?;
+
+    Ok((image, view))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_counts_must_be_supported_powers_of_two() {
+        assert_eq!(msaa_samples_from_count(1).unwrap(), vk::SampleCountFlags::_1);
+        assert_eq!(msaa_samples_from_count(8).unwrap(), vk::SampleCountFlags::_8);
+        assert_eq!(msaa_samples_from_count(64).unwrap(), vk::SampleCountFlags::_64);
+
+        assert!(msaa_samples_from_count(0).is_err());
+        assert!(msaa_samples_from_count(3).is_err());
+        assert!(msaa_samples_from_count(128).is_err());
+    }
+
+    #[test]
+    fn next_sample_count_wraps_after_the_max() {
+        let max = vk::SampleCountFlags::_8;
+        assert_eq!(next_msaa_samples(vk::SampleCountFlags::_1, max), vk::SampleCountFlags::_2);
+        assert_eq!(next_msaa_samples(vk::SampleCountFlags::_4, max), vk::SampleCountFlags::_8);
+        assert_eq!(next_msaa_samples(vk::SampleCountFlags::_8, max), vk::SampleCountFlags::_1);
+        assert_eq!(next_msaa_samples(vk::SampleCountFlags::_1, vk::SampleCountFlags::_1), vk::SampleCountFlags::_1);
+        assert_eq!(next_msaa_samples(vk::SampleCountFlags::_64, vk::SampleCountFlags::_64), vk::SampleCountFlags::_1);
+    }
+}
@@ -0,0 +1,54 @@
+use vulkanalia::prelude::v1_0::*;
+use anyhow::{anyhow, Result};
+use log::*;
+
+use crate::allocator::*;
+use crate::create_image::*;
+use crate::resources::*;
+
+/// The depth formats to use, in order of preference.
+const DEPTH_FORMATS: &[vk::Format] = &[
+    vk::Format::D32_SFLOAT,
+    vk::Format::D32_SFLOAT_S8_UINT,
+    vk::Format::D24_UNORM_S8_UINT,
+];
+
+/// The first depth format the device supports as an optimally tiled attachment.
+pub unsafe fn get_depth_format(instance: &Instance, physical_device: &vk::PhysicalDevice) -> Result<vk::Format> {
+    let format = DEPTH_FORMATS
+        .iter()
+        .cloned()
+        .find(|f| {
+            let properties = instance.get_physical_device_format_properties(*physical_device, *f);
+            properties.optimal_tiling_features.contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+        })
+        .ok_or_else(|| anyhow!("Failed to find a supported depth format."))?;
+
+    debug!("Depth format: {:?}", format);
+
+    Ok(format)
+}
+
+/// Creates the depth image drawn together with the swapchain images, it has to be recreated with the swapchain.
+/// It has as many samples as the color attachment it's drawn with.
+pub unsafe fn create_depth_objects(
+    device: &SharedDevice,
+    allocator: &MemoryAllocator,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    samples: vk::SampleCountFlags,
+) -> Result<(Image, ImageView)> {
+    let image = create_image(
+        allocator,
+        extent,
+        1,
+        samples,
+        format,
+        vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+
+    let view = create_image_view(device, *image, format, vk::ImageAspectFlags::DEPTH, 1)?;
+
+    Ok((image, view))
+}
@@ -3,73 +3,129 @@ use anyhow::Result;
 use std::mem::size_of;
 
 use crate::UniformBufferObject;
+use crate::allocator::Buffer;
+use crate::resources::*;
+use crate::render_graph::CHANNEL_COUNT;
+
+/// The binding of the image of `iChannel0`, the other channels follow it.
+pub const CHANNEL_BINDING: u32 = 1;
+
+/// The binding of the sampler of `iChannel0`, the samplers of the other channels follow it.
+pub const SAMPLER_BINDING: u32 = CHANNEL_BINDING + CHANNEL_COUNT as u32;
 
 pub unsafe fn create_descriptor_sets(
     device: &Device,
     descriptor_set_layout: &vk::DescriptorSetLayout,
     descriptor_pool: &vk::DescriptorPool,
     pool_size: usize,
-    uniform_buffers: &Vec<vk::Buffer>
+    uniform_buffers: &[Buffer],
+    channels: &[vk::DescriptorImageInfo; CHANNEL_COUNT],
 ) -> Result<Vec<vk::DescriptorSet>> {
     let layouts = vec![*descriptor_set_layout; pool_size];
     let info = vk::DescriptorSetAllocateInfo::builder()
         .descriptor_pool(*descriptor_pool)
         .set_layouts(&layouts);
-        
+
     let descriptor_sets = device.allocate_descriptor_sets(&info)?;
 
     for i in 0..pool_size {
-        for i in 0..pool_size {
-            let info = vk::DescriptorBufferInfo::builder()
-                .buffer(uniform_buffers[i])
-                .offset(0)
-                .range(size_of::<UniformBufferObject>() as u64);
-
-            let buffer_info = &[info];
-            let ubo_write = vk::WriteDescriptorSet::builder()
-                .dst_set(descriptor_sets[i])
-                .dst_binding(0)
-                .dst_array_element(0)
-                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-                .buffer_info(buffer_info);
-
-            device.update_descriptor_sets(&[ubo_write], &[] as &[vk::CopyDescriptorSet]);
-        }
+        let info = vk::DescriptorBufferInfo::builder()
+            .buffer(*uniform_buffers[i])
+            .offset(0)
+            .range(size_of::<UniformBufferObject>() as u64);
+
+        let buffer_info = &[info];
+        let ubo_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_sets[i])
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .buffer_info(buffer_info);
+
+        // Each channel's image and sampler are written from the same info, the unused half is ignored.
+        let channel_writes = channels.iter().enumerate().flat_map(|(channel, info)| {
+            [
+                (CHANNEL_BINDING, vk::DescriptorType::SAMPLED_IMAGE),
+                (SAMPLER_BINDING, vk::DescriptorType::SAMPLER),
+            ]
+            .map(|(binding, type_)| {
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_sets[i])
+                    .dst_binding(binding + channel as u32)
+                    .dst_array_element(0)
+                    .descriptor_type(type_)
+                    .image_info(std::slice::from_ref(info))
+                    .build()
+            })
+        });
+
+        let mut writes = vec![ubo_write.build()];
+        writes.extend(channel_writes);
+
+        device.update_descriptor_sets(&writes, &[] as &[vk::CopyDescriptorSet]);
     }
 
     Ok(descriptor_sets)
 }
 
-pub unsafe fn create_descriptor_pool(device: &Device, pool_size: u32) -> Result<vk::DescriptorPool> {
+pub unsafe fn create_descriptor_pool(device: &SharedDevice, pool_size: u32) -> Result<DescriptorPool> {
     let ubo_size = vk::DescriptorPoolSize::builder()
         .type_(vk::DescriptorType::UNIFORM_BUFFER)
         .descriptor_count(pool_size);
 
-    let pool_sizes = &[ubo_size];
+    let channel_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::SAMPLED_IMAGE)
+        .descriptor_count(pool_size * CHANNEL_COUNT as u32);
+
+    let sampler_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::SAMPLER)
+        .descriptor_count(pool_size * CHANNEL_COUNT as u32);
+
+    let pool_sizes = &[ubo_size, channel_size, sampler_size];
     let info = vk::DescriptorPoolCreateInfo::builder()
         .pool_sizes(pool_sizes)
         .max_sets(pool_size);
 
-    let descriptor_pool = device.create_descriptor_pool(&info, None)?;
+    let descriptor_pool = Owned::new(device, device.create_descriptor_pool(&info, None)?);
 
     Ok(descriptor_pool)
 }
 
 pub unsafe fn create_descriptor_set_layout(
-    device: &Device,
-) -> Result<vk::DescriptorSetLayout> {
+    device: &SharedDevice,
+) -> Result<DescriptorSetLayout> {
     let ubo_binding = vk::DescriptorSetLayoutBinding::builder()
         .binding(0)
         .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
         .descriptor_count(1)
         // .stage_flags(vk::ShaderStageFlags::VERTEX & vk::ShaderStageFlags::FRAGMENT)
-        .stage_flags(vk::ShaderStageFlags::ALL);
+        .stage_flags(vk::ShaderStageFlags::ALL)
+        .build();
+
+    // The shader compiler has no combined image samplers, so every channel is an image and a sampler binding
+    // which the prelude combines into a `sampler2D`.
+    let channel_bindings = (0..CHANNEL_COUNT as u32).flat_map(|channel| {
+        [
+            (CHANNEL_BINDING, vk::DescriptorType::SAMPLED_IMAGE),
+            (SAMPLER_BINDING, vk::DescriptorType::SAMPLER),
+        ]
+        .map(|(binding, type_)| {
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(binding + channel)
+                .descriptor_type(type_)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                .build()
+        })
+    });
+
+    let mut bindings = vec![ubo_binding];
+    bindings.extend(channel_bindings);
 
-    let bindings = &[ubo_binding];
     let info = vk::DescriptorSetLayoutCreateInfo::builder()
-        .bindings(bindings);        
-    
-    let descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
+        .bindings(&bindings);
+
+    let descriptor_set_layout = Owned::new(device, device.create_descriptor_set_layout(&info, None)?);
 
     Ok(descriptor_set_layout)
-}
\ No newline at end of file
+}
@@ -1,21 +1,31 @@
 use vulkanalia::prelude::v1_0::*;
 use anyhow::Result;
 
-pub unsafe fn create_framebuffers(device: &Device, swapchain_image_views: &Vec<vk::ImageView>, render_pass: &vk::RenderPass, swapchain_extent: &vk::Extent2D, out_framebuffers: &mut Vec<vk::Framebuffer>) -> Result<()> {
+use crate::resources::*;
+
+/// Creates a framebuffer per swapchain image, all sharing the depth image.
+///
+/// With MSAA all of them also share the multisampled `color_image_view`, which is resolved into the swapchain
+/// image. Without it `color_image_view` is null and they draw to the swapchain image directly.
+pub unsafe fn create_framebuffers(device: &SharedDevice, swapchain_image_views: &[ImageView], color_image_view: vk::ImageView, depth_image_view: vk::ImageView, render_pass: &vk::RenderPass, swapchain_extent: &vk::Extent2D, out_framebuffers: &mut Vec<Framebuffer>) -> Result<()> {
     let framebuffers = swapchain_image_views
         .iter()
         .map(|i| {
-            let attachments = &[*i];
+            let attachments = if color_image_view.is_null() {
+                vec![**i, depth_image_view]
+            } else {
+                vec![color_image_view, depth_image_view, **i]
+            };
             let create_info = vk::FramebufferCreateInfo::builder()
                 .render_pass(*render_pass)
-                .attachments(attachments)
+                .attachments(&attachments)
                 .width(swapchain_extent.width)
                 .height(swapchain_extent.height)
                 .layers(1);
 
-            device.create_framebuffer(&create_info, None)
+            Ok(Owned::new(device, device.create_framebuffer(&create_info, None)?))
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect::<Result<Vec<_>>>()?;
 
     *out_framebuffers = framebuffers;
 
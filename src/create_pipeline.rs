@@ -1,85 +1,177 @@
 use vulkanalia::prelude::v1_0::*;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 
+use crate::push_constants::*;
+use crate::resources::*;
 use crate::shader_manager::*;
 use crate::vertex::*;
 
-pub unsafe fn create_pipeline(device: &Device, shader_manager: &ShaderManager, swapchain_extent: &vk::Extent2D, descriptor_set_layout: &vk::DescriptorSetLayout, render_pass: &vk::RenderPass ) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
-    let bytecode = shader_manager.get_shaders_bytecode()?;
+/// How a pipeline uses the depth buffer of its render pass.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DepthOptions {
+    /// Discards fragments behind what was already drawn.
+    pub test: bool,
+    /// Stores the depth of the drawn fragments.
+    pub write: bool,
+}
+
+impl DepthOptions {
+    pub const ENABLED: Self = Self { test: true, write: true };
+    pub const DISABLED: Self = Self { test: false, write: false };
+}
+
+impl Default for DepthOptions {
+    fn default() -> Self {
+        Self::ENABLED
+    }
+}
+
+/// How the color of a fragment is combined with the color already in the attachment.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Replaces the color.
+    #[default]
+    Opaque,
+    /// Mixes by the fragment's alpha, `src * a + dst * (1 - a)`.
+    Alpha,
+    /// Adds the fragment's color weighted by its alpha, `src * a + dst`.
+    Additive,
+    /// Mixes a color already multiplied by its alpha, `src + dst * (1 - a)`.
+    Premultiplied,
+}
+
+impl BlendMode {
+    fn attachment_state(self) -> vk::PipelineColorBlendAttachmentState {
+        let (enable, src_color, dst_color) = match self {
+            Self::Opaque => (false, vk::BlendFactor::ONE, vk::BlendFactor::ZERO),
+            Self::Alpha => (true, vk::BlendFactor::SRC_ALPHA, vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+            Self::Additive => (true, vk::BlendFactor::SRC_ALPHA, vk::BlendFactor::ONE),
+            Self::Premultiplied => (true, vk::BlendFactor::ONE, vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+        };
+
+        // Alpha is combined like premultiplied colors in every mode, so it ends up as the coverage.
+        vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(enable)
+            .src_color_blend_factor(src_color)
+            .dst_color_blend_factor(dst_color)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(if enable { vk::BlendFactor::ONE_MINUS_SRC_ALPHA } else { vk::BlendFactor::ZERO })
+            .alpha_blend_op(vk::BlendOp::ADD)
+            .build()
+    }
+}
+
+/// The vertex buffer bindings and attributes a pipeline reads.
+#[derive(Clone, Debug, Default)]
+pub struct VertexLayout {
+    pub bindings: Vec<vk::VertexInputBindingDescription>,
+    pub attributes: Vec<vk::VertexInputAttributeDescription>,
+}
+
+impl VertexLayout {
+    /// The layout of `Vertex`, used by the quad and meshes.
+    pub fn vertex() -> Self {
+        Self {
+            bindings: vec![Vertex::binding_description()],
+            attributes: Vertex::attribute_descriptions().to_vec(),
+        }
+    }
+}
+
+/// Everything about a graphics pipeline that isn't shared by all pipelines, which are the descriptor set and push
+/// constant layout and the dynamic viewport.
+#[derive(Clone, Debug)]
+pub struct PipelineDesc {
+    /// The vertex and fragment shader.
+    pub stages: ShaderByteCode,
+    pub vertex_layout: VertexLayout,
+    pub topology: vk::PrimitiveTopology,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    /// Anything but `FILL` needs the `fillModeNonSolid` device feature.
+    pub polygon_mode: vk::PolygonMode,
+    pub blend: BlendMode,
+    pub depth: DepthOptions,
+    /// Must match the sample count of the render pass attachments.
+    pub samples: vk::SampleCountFlags,
+}
+
+impl PipelineDesc {
+    /// Opaque, depth tested triangle lists of `Vertex`, culling the back faces of counter-clockwise models.
+    pub fn new(stages: ShaderByteCode) -> Self {
+        Self {
+            stages,
+            vertex_layout: VertexLayout::vertex(),
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            cull_mode: vk::CullModeFlags::BACK,
+            // Counter-clockwise in model space, the flipped viewport (see `record_viewport`) mirrors the winding.
+            front_face: vk::FrontFace::CLOCKWISE,
+            polygon_mode: vk::PolygonMode::FILL,
+            blend: BlendMode::Opaque,
+            depth: DepthOptions::ENABLED,
+            samples: vk::SampleCountFlags::_1,
+        }
+    }
+}
 
-    let vert_shader_module = create_shader_module(device, &bytecode.vertex)?;
-    let frag_shader_module = create_shader_module(device, &bytecode.fragment)?;
+/// Creates a pipeline with dynamic viewport and scissor state, they're set by `record_viewport` when recording.
+pub unsafe fn create_pipeline(device: &SharedDevice, pipeline_cache: vk::PipelineCache, desc: &PipelineDesc, descriptor_set_layout: &vk::DescriptorSetLayout, render_pass: &vk::RenderPass) -> Result<(PipelineLayout, Pipeline)> {
+    // The modules are only needed during creation, they're dropped when it's done whether it succeeded or not.
+    let vert_shader_module = create_shader_module(device, &desc.stages.vertex)?;
+    let frag_shader_module = create_shader_module(device, &desc.stages.fragment)?;
 
     let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::VERTEX)
-        .module(vert_shader_module)
+        .module(*vert_shader_module)
         .name(b"main\0");
     
     let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::FRAGMENT)
-        .module(frag_shader_module)
+        .module(*frag_shader_module)
         .name(b"main\0");
 
-    let binding_descriptions = &[Vertex::binding_description()];
-    let attribute_descriptions = Vertex::attribute_descriptions();
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
-        .vertex_binding_descriptions(binding_descriptions)
-        .vertex_attribute_descriptions(&attribute_descriptions);
+        .vertex_binding_descriptions(&desc.vertex_layout.bindings)
+        .vertex_attribute_descriptions(&desc.vertex_layout.attributes);
 
     let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .topology(desc.topology)
         .primitive_restart_enable(false);
 
-    // let viewport = vk::Viewport::builder()
-    //     .x(0.0)
-    //     .y(0.0)
-    //     .width(swapchain_extent.width as f32)
-    //     .height(swapchain_extent.height as f32)
-    //     .min_depth(0.0)
-    //     .max_depth(1.0);
-
-    let viewport = vk::Viewport::builder()
-        .x(0.0)
-        .y(swapchain_extent.height as f32)
-        .width(swapchain_extent.width as f32)
-        .height(-(swapchain_extent.height as f32))
-        .min_depth(0.0)
-        .max_depth(1.0);
-
-    let scissor = vk::Rect2D::builder()
-        .offset(vk::Offset2D { x: 0, y: 0 })
-        .extent(*swapchain_extent);
-
-    let viewports = &[viewport];
-    let scissors = &[scissor];
+    // The viewport and scissor are dynamic, only their count is part of the pipeline.
     let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
-        .viewports(viewports)
-        .scissors(scissors);
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(dynamic_states);
 
     let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
         .depth_clamp_enable(false)
         .rasterizer_discard_enable(false)
-        .polygon_mode(vk::PolygonMode::FILL)
+        .polygon_mode(desc.polygon_mode)
         .line_width(1.0)
-        .cull_mode(vk::CullModeFlags::BACK)
-        .front_face(vk::FrontFace::CLOCKWISE)
+        .cull_mode(desc.cull_mode)
+        .front_face(desc.front_face)
         .depth_bias_enable(false);
 
     let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
         .sample_shading_enable(false)
-        .rasterization_samples(vk::SampleCountFlags::_1);
-
-    let attachment = vk::PipelineColorBlendAttachmentState::builder()
-        .color_write_mask(vk::ColorComponentFlags::all())
-        .blend_enable(false)
-        .src_color_blend_factor(vk::BlendFactor::ONE)  // Optional
-        .dst_color_blend_factor(vk::BlendFactor::ZERO) // Optional
-        .color_blend_op(vk::BlendOp::ADD)              // Optional
-        .src_alpha_blend_factor(vk::BlendFactor::ONE)  // Optional
-        .dst_alpha_blend_factor(vk::BlendFactor::ZERO) // Optional
-        .alpha_blend_op(vk::BlendOp::ADD);             // Optional
-
-    let attachments = &[attachment];
+        .rasterization_samples(desc.samples);
+
+    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(desc.depth.test)
+        .depth_write_enable(desc.depth.write)
+        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_bounds_test_enable(false)
+        .min_depth_bounds(0.0) // Optional.
+        .max_depth_bounds(1.0) // Optional.
+        .stencil_test_enable(false);
+
+    let attachments = &[desc.blend.attachment_state()];
     let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
         .logic_op_enable(false)
         .logic_op(vk::LogicOp::COPY)
@@ -88,10 +180,12 @@ pub unsafe fn create_pipeline(device: &Device, shader_manager: &ShaderManager, s
 
 
     let set_layouts = &[*descriptor_set_layout];
+    let push_constant_ranges = &[PushConstants::range()];
     let layout_info = vk::PipelineLayoutCreateInfo::builder()
-        .set_layouts(set_layouts);
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
 
-    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+    let pipeline_layout = Owned::new(device, device.create_pipeline_layout(&layout_info, None)?);
 
     let stages = &[vert_stage, frag_stage];
     let info = vk::GraphicsPipelineCreateInfo::builder()
@@ -101,33 +195,46 @@ pub unsafe fn create_pipeline(device: &Device, shader_manager: &ShaderManager, s
         .viewport_state(&viewport_state)
         .rasterization_state(&rasterization_state)
         .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
         .color_blend_state(&color_blend_state)
-        .layout(pipeline_layout)
+        .dynamic_state(&dynamic_state)
+        .layout(*pipeline_layout)
         .render_pass(*render_pass)
         .subpass(0);
 
-    let pipeline = device.create_graphics_pipelines(
-        vk::PipelineCache::null(), &[info], None)?.0;
+    let (pipeline, _) = device.create_graphics_pipelines(
+        pipeline_cache, &[info], None)?;
 
-    device.destroy_shader_module(vert_shader_module, None);
-    device.destroy_shader_module(frag_shader_module, None);
+    Ok((pipeline_layout, Owned::new(device, pipeline)))
+}
 
-    Ok((pipeline_layout, pipeline))
+/// Sets the viewport and scissor of the pipelines from `create_pipeline` to cover `extent`.
+///
+/// The viewport is flipped with a negative height, so y points up like in OpenGL and Shadertoy.
+pub unsafe fn record_viewport(device: &Device, command_buffer: vk::CommandBuffer, extent: vk::Extent2D) {
+    let viewport = vk::Viewport::builder()
+        .x(0.0)
+        .y(extent.height as f32)
+        .width(extent.width as f32)
+        .height(-(extent.height as f32))
+        .min_depth(0.0)
+        .max_depth(1.0);
+
+    let scissor = vk::Rect2D::builder()
+        .offset(vk::Offset2D { x: 0, y: 0 })
+        .extent(extent);
+
+    device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+    device.cmd_set_scissor(command_buffer, 0, &[scissor]);
 }
 
 unsafe fn create_shader_module(
-    device: &Device,
-    bytecode: &[u8],
-) -> Result<vk::ShaderModule> {
-    let bytecode = Vec::<u8>::from(bytecode);
-    let (prefix, code, suffix) = bytecode.align_to::<u32>();
-    if !prefix.is_empty() || !suffix.is_empty() {
-        return Err(anyhow!("Shader bytecode is not properly aligned."));
-    }
-
+    device: &SharedDevice,
+    code: &[u32],
+) -> Result<ShaderModule> {
     let info = vk::ShaderModuleCreateInfo::builder()
-        .code_size(bytecode.len())
+        .code_size(std::mem::size_of_val(code))
         .code(code);
 
-    Ok(device.create_shader_module(&info, None)?)
+    Ok(Owned::new(device, device.create_shader_module(&info, None)?))
 }
\ No newline at end of file
@@ -4,15 +4,18 @@ use winit::window::Window;
 use anyhow::Result;
 
 use crate::queue_family_indices::*;
+use crate::resources::*;
 use crate::swapchain_support::*;
 
 pub struct CreateSwapchainData {
     pub surface: vk::SurfaceKHR,
     pub physical_device: vk::PhysicalDevice,
+    /// Used if the surface supports it, FIFO otherwise.
+    pub present_mode: vk::PresentModeKHR,
 }
 
 pub struct CreateSwapchainOutput {
-    pub swapchain: vk::SwapchainKHR,
+    pub swapchain: Swapchain,
     pub swapchain_format: vk::Format,
     pub swapchain_extent: vk::Extent2D,
     pub swapchain_images: Vec<vk::Image>,
@@ -21,14 +24,14 @@ pub struct CreateSwapchainOutput {
 pub unsafe fn create_swapchain(
     window: &Window,
     instance: &Instance,
-    device: &Device,
+    device: &SharedDevice,
     data: &CreateSwapchainData
 ) -> Result<CreateSwapchainOutput> {
     let indices = QueueFamilyIndices::get(instance, &data.surface, data.physical_device)?;
     let support = SwapchainSupport::get(instance, &data.surface, data.physical_device)?;
 
     let surface_format = get_swapchain_surface_format(&support.formats);
-    let present_mode = get_swapchain_present_mode(&support.present_modes);
+    let present_mode = get_swapchain_present_mode(&support.present_modes, data.present_mode);
     let extent = get_swapchain_extent(window, support.capabilities);
 
     let image_count = get_optimal_image_count(&support);
@@ -58,14 +61,14 @@ pub unsafe fn create_swapchain(
         .clipped(true)
         .old_swapchain(vk::SwapchainKHR::null());
 
-    let swapchain = device.create_swapchain_khr(&info, None)?;
+    let swapchain = Owned::new(device, device.create_swapchain_khr(&info, None)?);
 
-    return Ok (CreateSwapchainOutput {
+    Ok (CreateSwapchainOutput {
+        swapchain_images: device.get_swapchain_images_khr(*swapchain)?,
         swapchain,
-        swapchain_images: device.get_swapchain_images_khr(swapchain)?,
         swapchain_format: surface_format.format,
         swapchain_extent: extent
-    });
+    })
 }
 
 fn get_optimal_image_count(support: &SwapchainSupport) -> u32 {
@@ -91,13 +94,15 @@ fn get_swapchain_surface_format(
         .unwrap_or_else(|| formats[0])
 }
 
+/// The preferred present mode, or FIFO which every surface supports.
 fn get_swapchain_present_mode(
     present_modes: &[vk::PresentModeKHR],
+    preferred: vk::PresentModeKHR,
 ) -> vk::PresentModeKHR {
     present_modes
         .iter()
         .cloned()
-        .find(|m| *m == vk::PresentModeKHR::MAILBOX)
+        .find(|m| *m == preferred)
         .unwrap_or(vk::PresentModeKHR::FIFO)
 }
 
@@ -105,7 +110,7 @@ fn get_swapchain_extent(
     window: &Window,
     capabilities: vk::SurfaceCapabilitiesKHR,
 ) -> vk::Extent2D {
-    if capabilities.current_extent.width != u32::max_value() {
+    if capabilities.current_extent.width != u32::MAX {
         capabilities.current_extent
     } else {
         let size = window.inner_size();
@@ -125,5 +130,5 @@ fn get_swapchain_extent(
 }
 
 fn clamp(min: u32, max: u32, v: u32) -> u32 {
-    return min.max(max.min(v));
+    min.max(max.min(v))
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+use thiserror::Error;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::shader_manager::ShaderError;
+
+/// The failures of an `Engine` or `Headless` renderer a caller may want to tell apart and recover from.
+#[derive(Debug, Error)]
+pub enum EngineError {
+    /// No physical device has what the renderer needs, the reason for each is logged as a warning.
+    #[error("Failed to find a suitable physical device.")]
+    NoSuitableDevice,
+    /// Instance extensions the Vulkan implementation doesn't provide, usually the surface extensions of the window
+    /// system.
+    #[error("Missing instance extensions: {}.", .0.join(", "))]
+    MissingExtensions(Vec<String>),
+    /// The surface changed and the swapchain no longer matches it, rendering again after `resize` recovers.
+    #[error("The swapchain is out of date.")]
+    SwapchainOutOfDate,
+    #[error(transparent)]
+    Shader(#[from] ShaderError),
+    #[error("Out of {} memory.", if *device { "device" } else { "host" })]
+    OutOfMemory { device: bool },
+    #[error("The device was lost.")]
+    DeviceLost,
+    #[error("Vulkan error: {0}.")]
+    Vulkan(vk::ErrorCode),
+    #[error("{0:#}")]
+    Other(anyhow::Error),
+}
+
+impl From<vk::ErrorCode> for EngineError {
+    fn from(code: vk::ErrorCode) -> Self {
+        match code {
+            vk::ErrorCode::OUT_OF_DATE_KHR => Self::SwapchainOutOfDate,
+            vk::ErrorCode::OUT_OF_HOST_MEMORY => Self::OutOfMemory { device: false },
+            vk::ErrorCode::OUT_OF_DEVICE_MEMORY => Self::OutOfMemory { device: true },
+            vk::ErrorCode::DEVICE_LOST => Self::DeviceLost,
+            code => Self::Vulkan(code),
+        }
+    }
+}
+
+/// Recovers the typed error from the `anyhow` errors used internally, anything else becomes `Other`. Vulkan error
+/// codes are found anywhere in the chain, so context added on the way up keeps them.
+impl From<anyhow::Error> for EngineError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<EngineError>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+
+        let error = match error.downcast::<ShaderError>() {
+            Ok(error) => return Self::Shader(error),
+            Err(error) => error,
+        };
+
+        match error.chain().find_map(|e| e.downcast_ref::<vk::ErrorCode>()).copied() {
+            Some(code) => code.into(),
+            None => Self::Other(error),
+        }
+    }
+}
+
+/// Why a physical device can't be used, logged while picking one.
+#[derive(Debug, Error)]
+pub enum SuitabilityError {
+    #[error("Vulkan {major}.{minor} is older than 1.1.")]
+    ApiVersion { major: u32, minor: u32 },
+    #[error("Missing geometry shader support.")]
+    GeometryShader,
+    #[error("Missing required queue families.")]
+    QueueFamilies,
+    #[error("Missing required device extensions: {}.", .0.join(", "))]
+    Extensions(Vec<String>),
+    #[error("Insufficient swapchain support.")]
+    Swapchain,
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+
+    use super::*;
+
+    #[test]
+    fn vulkan_errors_keep_their_code_through_context() {
+        let error = Err::<(), _>(vk::ErrorCode::DEVICE_LOST).context("Failed to submit the frame").unwrap_err();
+        assert!(matches!(EngineError::from(error), EngineError::DeviceLost));
+
+        let error = anyhow!(vk::ErrorCode::OUT_OF_DEVICE_MEMORY);
+        assert!(matches!(EngineError::from(error), EngineError::OutOfMemory { device: true }));
+    }
+
+    #[test]
+    fn typed_errors_are_recovered() {
+        let error = anyhow::Error::from(EngineError::NoSuitableDevice);
+        assert!(matches!(EngineError::from(error), EngineError::NoSuitableDevice));
+
+        let error = anyhow::Error::from(ShaderError::Compile(Vec::new()));
+        assert!(matches!(EngineError::from(error), EngineError::Shader(_)));
+
+        assert!(matches!(EngineError::from(anyhow!("Something else")), EngineError::Other(_)));
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+
+use anyhow::Result;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::push_constants::*;
+use crate::resources::*;
+
+/// The command pool and buffer of one frame in flight, recorded anew every time the frame is rendered.
+#[derive(Debug, Default)]
+pub struct FrameCommands {
+    pub command_pool: CommandPool,
+    pub command_buffer: vk::CommandBuffer,
+}
+
+impl FrameCommands {
+    pub unsafe fn create(device: &SharedDevice, queue_family: u32) -> Result<Self> {
+        let info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(queue_family);
+
+        let command_pool = Owned::new(device, device.create_command_pool(&info, None)?);
+
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(*command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+
+        let command_buffer = device.allocate_command_buffers(&allocate_info)?[0];
+
+        Ok(Self { command_pool, command_buffer })
+    }
+
+    /// Resets the pool and begins recording the command buffer, the previous submission of this frame must have
+    /// completed.
+    pub unsafe fn begin(&self, device: &Device) -> Result<vk::CommandBuffer> {
+        device.reset_command_pool(*self.command_pool, vk::CommandPoolResetFlags::empty())?;
+
+        let info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        device.begin_command_buffer(self.command_buffer, &info)?;
+
+        Ok(self.command_buffer)
+    }
+}
+
+/// Where a `DrawHook` records its draw calls.
+#[derive(Copy, Clone, Debug)]
+pub struct DrawContext<'a> {
+    pub command_buffer: vk::CommandBuffer,
+    /// The swapchain image drawn to, which selects the descriptor sets of the frame.
+    pub image_index: usize,
+    pub extent: vk::Extent2D,
+    /// The layout of the image pass pipeline, for pushing constants or binding other descriptor sets.
+    pub pipeline_layout: vk::PipelineLayout,
+    pub push_constants: &'a PushConstants,
+}
+
+/// Scene code adding its own draw calls to every frame.
+///
+/// Hooks are called in order inside the image pass, after the scene's own draw and with its pipeline, descriptor
+/// sets and push constants still bound. A hook binding another pipeline doesn't need to restore them.
+///
+/// Recording needs `unsafe` Vulkan calls, the command buffer is valid and recording for the whole call.
+///
+/// A hook drawing with its own Vulkan objects creates them in `upload_assets` and destroys them in
+/// `destroy_assets`, the engine calls both again when it replaces the render pass (for another MSAA sample count or
+/// swapchain format) and when it recreates the device after losing it.
+pub trait DrawHook: fmt::Debug {
+    fn record(&mut self, device: &Device, context: &DrawContext);
+
+    /// Creates the hook's Vulkan objects and uploads its assets, once the engine is created and after every render
+    /// pass or device recreation.
+    fn upload_assets(&mut self, raw: RawHandles) -> Result<()> {
+        Ok(())
+    }
+
+    /// Destroys what `upload_assets` created, before the engine drops its device. After a device loss the objects
+    /// can still be destroyed, but nothing waits on them anymore.
+    fn destroy_assets(&mut self, raw: RawHandles) {}
+}
+
+/// The Vulkan objects behind an `Engine` or `Headless` renderer, the escape hatch for code going beyond what they
+/// offer.
+///
+/// The renderer keeps owning them: they must not be destroyed, and they're only valid until the renderer is
+/// dropped or recreates its device or, for the render pass and extent, its swapchain is recreated. Using them needs
+/// `unsafe`, like every Vulkan call.
+#[derive(Copy, Clone, Debug)]
+pub struct RawHandles<'a> {
+    pub instance: &'a Instance,
+    pub device: &'a Device,
+    pub physical_device: vk::PhysicalDevice,
+    pub graphics_queue: vk::Queue,
+    /// A graphics command pool for one time commands, used from the renderer's thread only.
+    pub command_pool: vk::CommandPool,
+    /// The render pass of the image pass, which `DrawHook`s record into.
+    pub render_pass: vk::RenderPass,
+    pub descriptor_set_layout: vk::DescriptorSetLayout,
+    pub pipeline_cache: vk::PipelineCache,
+    /// The sample count of `render_pass`, pipelines drawn in it need the same.
+    pub msaa_samples: vk::SampleCountFlags,
+    pub extent: vk::Extent2D,
+}
@@ -0,0 +1,189 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_1::*;
+
+use crate::config::Config;
+use crate::headless::*;
+use crate::shader_manager::*;
+use crate::texture::read_png;
+
+/// Options for the golden-image regression run, given as
+/// `--golden <reference_dir> [--size <width>x<height>] [--time <seconds>] [--tolerance <0-255>] [--bless]`. The shaders
+/// dir and validation come from the `Config`.
+#[derive(Clone, Debug)]
+pub struct GoldenOptions {
+    pub reference_dir: PathBuf,
+    pub extent: vk::Extent2D,
+    pub time: f32,
+    /// The largest difference allowed in any channel of a pixel before it counts as mismatched.
+    pub tolerance: u8,
+    /// Writes the rendered images as the new references instead of comparing against them.
+    pub bless: bool,
+}
+
+impl GoldenOptions {
+    /// Parses the golden options from the command line, `None` if `--golden` isn't given. Flags other than these
+    /// and the config's are rejected.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let args = args.skip(1).collect::<Vec<_>>();
+        if !args.iter().any(|a| a == "--golden") {
+            return Ok(None);
+        }
+
+        let mut reference_dir = None;
+        let mut extent = vk::Extent2D { width: 256, height: 256 };
+        let mut time = 1.0;
+        let mut tolerance = 2;
+        let mut bless = false;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| anyhow!("Missing value for '{}'.", arg));
+            match arg.as_str() {
+                "--golden" => reference_dir = Some(PathBuf::from(value()?)),
+                "--size" => extent = parse_extent(&value()?)?,
+                "--time" => time = value()?.parse()?,
+                "--tolerance" => tolerance = value()?.parse()?,
+                "--bless" => bless = true,
+                _ => skip_config_flag(&arg, &mut args)?,
+            }
+        }
+
+        Ok(reference_dir.map(|reference_dir| Self { reference_dir, extent, time, tolerance, bless }))
+    }
+}
+
+/// The result of comparing a rendered image against its reference.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    /// RGBA8 image highlighting mismatched pixels in red over a dimmed copy of the reference.
+    pub diff: Vec<u8>,
+}
+
+/// Compares two tightly packed RGBA8 images of the same size.
+pub fn compare_images(expected: &[u8], actual: &[u8], tolerance: u8) -> Comparison {
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let mut diff = Vec::with_capacity(expected.len());
+
+    for (e, a) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        let difference = e.iter().zip(a).map(|(e, a)| e.abs_diff(*a)).max().unwrap_or(0);
+        max_difference = max_difference.max(difference);
+
+        if difference > tolerance {
+            mismatched_pixels += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 12) as u8;
+            diff.extend_from_slice(&[luma, luma, luma, 255]);
+        }
+    }
+
+    Comparison { mismatched_pixels, max_difference, diff }
+}
+
+/// Renders every fragment shader in the shaders dir and compares it against `<reference_dir>/<shader>.png`,
+/// then every scene against `<reference_dir>/<scene>.scene.png`.
+///
+/// Mismatches write `<name>.actual.png` and `<name>.diff.png` next to the reference.
+pub fn run_golden_tests(config: &Config, options: &GoldenOptions) -> Result<()> {
+    let shader_manager = ShaderManager::create_in(&config.shader_dir()?)?;
+    let mut failures = Vec::new();
+
+    let fragment_shaders = shader_manager
+        .fragment_shaders()?
+        .into_iter()
+        .map(|f| (f.trim_end_matches(".frag").to_string(), shader_manager.clone().with_fragment_shader(&f)));
+    let scenes = shader_manager
+        .scenes()?
+        .into_iter()
+        .map(|s| (s.clone(), shader_manager.clone().with_scene(&s)));
+
+    for (name, shader_manager) in fragment_shaders.chain(scenes) {
+        let expected_path = options.reference_dir.join(format!("{}.png", name));
+        let actual_path = options.reference_dir.join(format!("{}.actual.png", name));
+        let diff_path = options.reference_dir.join(format!("{}.diff.png", name));
+
+        let actual = Headless::create(options.extent, shader_manager, config.validation)?.render(options.time)?;
+
+        if options.bless {
+            write_png(&expected_path, options.extent, &actual)?;
+            continue;
+        }
+
+        if !expected_path.exists() {
+            write_png(&actual_path, options.extent, &actual)?;
+            failures.push(format!("{}: no reference image {:?}, rerun with --bless to create it", name, expected_path));
+            continue;
+        }
+
+        let (expected_extent, expected) = read_png(&expected_path)?;
+        if expected_extent != options.extent {
+            write_png(&actual_path, options.extent, &actual)?;
+            failures.push(format!(
+                "{}: reference is {}x{} but rendered {}x{}",
+                name, expected_extent.width, expected_extent.height, options.extent.width, options.extent.height
+            ));
+            continue;
+        }
+
+        let comparison = compare_images(&expected, &actual, options.tolerance);
+        if comparison.mismatched_pixels > 0 {
+            write_png(&actual_path, options.extent, &actual)?;
+            write_png(&diff_path, options.extent, &comparison.diff)?;
+            failures.push(format!(
+                "{}: {} pixels differ by more than {} (max difference {}), see {:?}",
+                name, comparison.mismatched_pixels, options.tolerance, comparison.max_difference, diff_path
+            ));
+        } else {
+            info!("Golden image matches for {}.", name);
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Golden image mismatches:\n{}", failures.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differences_up_to_the_tolerance_match() {
+        let expected = [100, 100, 100, 255, 0, 0, 0, 255];
+        let actual = [102, 98, 100, 255, 0, 0, 0, 255];
+
+        let comparison = compare_images(&expected, &actual, 2);
+        assert_eq!(comparison.mismatched_pixels, 0);
+        assert_eq!(comparison.max_difference, 2);
+
+        let comparison = compare_images(&expected, &actual, 1);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.max_difference, 2);
+    }
+
+    #[test]
+    fn diff_marks_mismatches_over_the_dimmed_reference() {
+        let expected = [120, 60, 0, 255, 30, 30, 30, 255];
+        let actual = [120, 60, 0, 255, 30, 30, 200, 255];
+
+        let comparison = compare_images(&expected, &actual, 2);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.max_difference, 170);
+        assert_eq!(comparison.diff, [15, 15, 15, 255, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn alpha_differences_count() {
+        let comparison = compare_images(&[0, 0, 0, 255], &[0, 0, 0, 0], 0);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.max_difference, 255);
+    }
+}
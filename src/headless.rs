@@ -0,0 +1,381 @@
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::BufWriter;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::loader::{LibloadingLoader, LIBRARY};
+use vulkanalia::prelude::v1_1::*;
+
+use crate::config::config_flag_values;
+use crate::*;
+
+/// The format of the offscreen image, sRGB like the preferred swapchain format so the output matches the window.
+const HEADLESS_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+/// Renders the current shader into an offscreen image instead of a swapchain, for machines without a display.
+///
+/// The offscreen image takes the place of the swapchain images in `AppData`, so the regular render pass,
+/// pipeline, framebuffer, uniform buffer and command buffer paths are reused unchanged.
+///
+/// Like `Engine` it owns its Vulkan objects, which are destroyed when it's dropped, so using it needs no `unsafe`.
+#[derive(Debug)]
+pub struct Headless {
+    instance: SharedInstance,
+    device: SharedDevice,
+    data: AppData,
+    shader_manager: ShaderManager,
+    /// The image behind `data.swapchain_images`.
+    color_image: Image,
+    readback_buffer: Buffer,
+}
+
+impl Headless {
+    /// Creates an offscreen renderer drawing the shaders of `shader_manager` into an image of the given size, with
+    /// the validation layer if `validation` is set and the layer is installed.
+    pub fn create(extent: vk::Extent2D, shader_manager: ShaderManager, validation: bool) -> Result<Self, EngineError> {
+        unsafe { Ok(Self::create_objects(extent, shader_manager, validation)?) }
+    }
+
+    unsafe fn create_objects(extent: vk::Extent2D, shader_manager: ShaderManager, validation: bool) -> Result<Self> {
+        let loader = LibloadingLoader::new(LIBRARY)?;
+        let entry = Entry::new(loader).map_err(|b| anyhow!("{}", b))?;
+
+        let mut data = AppData { validation, ..Default::default() };
+        let instance = Rc::new(create_instance(None, entry, &mut data)?);
+
+        pick_physical_device(&instance, &mut data)?;
+
+        let device = create_logical_device(&instance, &mut data)?;
+        data.allocator = MemoryAllocator::new(&instance, &device, data.physical_device);
+
+        let color_image = create_image(
+            &data.allocator,
+            extent,
+            1,
+            vk::SampleCountFlags::_1,
+            HEADLESS_FORMAT,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        data.swapchain_extent = extent;
+        data.swapchain_format = HEADLESS_FORMAT;
+        data.swapchain_images = vec![*color_image];
+
+        create_swapchain_image_views(&device, &mut data)?;
+
+        data.depth_format = get_depth_format(&instance, &data.physical_device)?;
+        // Without MSAA, so golden images don't depend on the device's multisampling.
+        data.msaa_samples = vk::SampleCountFlags::_1;
+        create_render_targets(&device, &mut data)?;
+
+        create_render_pass(&instance, &device, &data.swapchain_format, data.depth_format, data.msaa_samples, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, &mut data.render_pass)?;
+        data.descriptor_set_layout = create_descriptor_set_layout(&device)?;
+        create_command_pool(&instance, &device, &mut data)?;
+        data.pipeline_cache = create_pipeline_cache(&instance, &device, data.physical_device, &pipeline_cache_path())?;
+
+        create_render_graph(&instance, &device, &shader_manager, &mut data)?;
+
+        create_framebuffers(&device, &data.swapchain_image_views, *data.color_image_view, *data.depth_image_view, &data.render_pass, &data.swapchain_extent, &mut data.framebuffers)?;
+        create_uniform_buffers(&mut data)?;
+        create_pass_descriptor_sets(&device, &mut data)?;
+
+        data.quad = Mesh::quad(&device, &data.allocator, &data.transfer)?;
+
+        create_frame_commands(&instance, &device, &mut data, 1)?;
+
+        let readback_buffer = create_buffer(
+            &data.allocator,
+            readback_size(extent),
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        Ok(Self {
+            instance,
+            device,
+            data,
+            shader_manager,
+            color_image,
+            readback_buffer,
+        })
+    }
+
+    /// Renders a single frame at the given time and returns its pixels as tightly packed RGBA8 rows.
+    pub fn render(&mut self, time: f32) -> Result<Vec<u8>, EngineError> {
+        // Waits for the frame to complete before returning, so no frame is in flight between calls.
+        unsafe { Ok(self.render_frame(time)?) }
+    }
+
+    /// The Vulkan handles behind the renderer, see `RawHandles`.
+    pub fn raw(&self) -> RawHandles<'_> {
+        RawHandles {
+            instance: &self.instance,
+            device: &self.device,
+            physical_device: self.data.physical_device,
+            graphics_queue: self.data.graphics_queue,
+            command_pool: *self.data.command_pool,
+            render_pass: *self.data.render_pass,
+            descriptor_set_layout: *self.data.descriptor_set_layout,
+            pipeline_cache: *self.data.pipeline_cache,
+            msaa_samples: self.data.msaa_samples,
+            extent: self.data.swapchain_extent,
+        }
+    }
+
+    unsafe fn render_frame(&mut self, time: f32) -> Result<Vec<u8>> {
+        let extent = self.data.swapchain_extent;
+        write_uniform_buffer(self.data.uniform_buffers[0].allocation(), &UniformBufferObject::still())?;
+
+        let command_buffer = self.data.frames[0].begin(&self.device)?;
+        let push_constants = PushConstants::new(extent, time, 0);
+        record_command_buffer(&self.device, &self.data, command_buffer, 0, &push_constants, &mut [])?;
+
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(*self.data.command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+
+        let copy_command_buffer = self.device.allocate_command_buffers(&allocate_info)?[0];
+        self.record_readback(copy_command_buffer)?;
+
+        let command_buffers = &[command_buffer, copy_command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(command_buffers);
+
+        self.device.queue_submit(self.data.graphics_queue, &[submit_info], vk::Fence::null())?;
+        self.device.queue_wait_idle(self.data.graphics_queue)?;
+
+        self.device.free_command_buffers(*self.data.command_pool, &[copy_command_buffer]);
+
+        let mut pixels = vec![0u8; readback_size(extent) as usize];
+        memcpy(self.readback_buffer.allocation().mapped_ptr()?, pixels.as_mut_ptr(), pixels.len());
+
+        Ok(pixels)
+    }
+
+    /// Records the copy of the rendered image into the host visible readback buffer.
+    unsafe fn record_readback(&self, command_buffer: vk::CommandBuffer) -> Result<()> {
+        let info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        self.device.begin_command_buffer(command_buffer, &info)?;
+
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        // The render pass leaves the image in TRANSFER_SRC_OPTIMAL, this only makes the color writes visible.
+        let image_barrier = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.data.swapchain_images[0])
+            .subresource_range(subresource_range);
+
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[image_barrier],
+        );
+
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        let extent = self.data.swapchain_extent;
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(subresource)
+            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 });
+
+        self.device.cmd_copy_image_to_buffer(
+            command_buffer,
+            self.data.swapchain_images[0],
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            *self.readback_buffer,
+            &[region],
+        );
+
+        let buffer_barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(*self.readback_buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE as u64);
+
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[buffer_barrier],
+            &[] as &[vk::ImageMemoryBarrier],
+        );
+
+        self.device.end_command_buffer(command_buffer)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Headless {
+    /// Waits for the device to finish and saves the pipeline cache, the Vulkan objects are destroyed as the
+    /// fields are dropped.
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device_wait_idle().ok();
+
+            if let Err(error) = save_pipeline_cache(&self.device, *self.data.pipeline_cache, &pipeline_cache_path()) {
+                warn!("Failed to save the pipeline cache: {}", error);
+            }
+        }
+    }
+}
+
+fn readback_size(extent: vk::Extent2D) -> vk::DeviceSize {
+    extent.width as vk::DeviceSize * extent.height as vk::DeviceSize * 4
+}
+
+/// Writes tightly packed RGBA8 pixels to a PNG file.
+pub fn write_png(path: &Path, extent: vk::Extent2D, pixels: &[u8]) -> Result<()> {
+    let file = File::create(path)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), extent.width, extent.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+
+    info!("Wrote {}x{} frame to {:?}", extent.width, extent.height, path);
+
+    Ok(())
+}
+
+/// Options for a headless render, given as `--headless <output.png> [--time <seconds>]`. The size, shader or scene
+/// and validation come from the `Config`.
+#[derive(Clone, Debug)]
+pub struct HeadlessOptions {
+    pub output: PathBuf,
+    pub time: f32,
+}
+
+impl HeadlessOptions {
+    /// Parses the headless options from the command line, `None` if `--headless` isn't given. Flags other than
+    /// these and the config's are rejected.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let args = args.skip(1).collect::<Vec<_>>();
+        if !args.iter().any(|a| a == "--headless") {
+            return Ok(None);
+        }
+
+        let mut output = None;
+        let mut time = 0.0;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| anyhow!("Missing value for '{}'.", arg));
+            match arg.as_str() {
+                "--headless" => output = Some(PathBuf::from(value()?)),
+                "--time" => time = value()?.parse()?,
+                _ => skip_config_flag(&arg, &mut args)?,
+            }
+        }
+
+        Ok(output.map(|output| Self { output, time }))
+    }
+}
+
+/// Skips a flag read by `Config::load` and its value, any other argument is an error.
+pub(crate) fn skip_config_flag(arg: &str, args: &mut impl Iterator<Item = String>) -> Result<()> {
+    let values = config_flag_values(arg).ok_or_else(|| anyhow!("Unexpected argument '{}'.", arg))?;
+    for _ in 0..values {
+        args.next().ok_or_else(|| anyhow!("Missing value for '{}'.", arg))?;
+    }
+
+    Ok(())
+}
+
+/// Parses a `<width>x<height>` size, both of which must be at least 1.
+pub fn parse_extent(value: &str) -> Result<vk::Extent2D> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or_else(|| anyhow!("Expected size as <width>x<height>, got '{}'.", value))?;
+
+    let extent = vk::Extent2D { width: width.parse()?, height: height.parse()? };
+    if extent.width == 0 || extent.height == 0 {
+        return Err(anyhow!("Expected a size of at least 1x1, got '{}'.", value));
+    }
+
+    Ok(extent)
+}
+
+/// Renders a single frame of the shader or scene set up by `config` without a window, at its size, and writes it to
+/// the output PNG.
+pub fn render_headless(config: &Config, options: &HeadlessOptions) -> Result<()> {
+    let shader_manager = config.shader_manager()?;
+    let pixels = Headless::create(config.size, shader_manager, config.validation)?.render(options.time)?;
+
+    write_png(&options.output, config.size, &pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        std::iter::once("engine").chain(args.iter().copied()).map(String::from).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn parse_extent_accepts_sizes() {
+        assert_eq!(parse_extent("64x48").unwrap(), vk::Extent2D { width: 64, height: 48 });
+    }
+
+    #[test]
+    fn parse_extent_rejects_empty_and_malformed_sizes() {
+        for value in ["0x0", "0x64", "64x0", "64", "64x", "x64", "-1x64"] {
+            assert!(parse_extent(value).is_err(), "{} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn options_are_only_parsed_with_headless() {
+        assert!(HeadlessOptions::from_args(args(&["--time", "2"])).unwrap().is_none());
+
+        let options = HeadlessOptions::from_args(args(&["--headless", "out.png", "--time", "2", "--size", "64x64"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.output, PathBuf::from("out.png"));
+        assert_eq!(options.time, 2.0);
+    }
+
+    #[test]
+    fn options_reject_unknown_flags() {
+        let error = HeadlessOptions::from_args(args(&["--headless", "out.png", "--sise", "64x64"])).unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected argument '--sise'.");
+
+        let error = HeadlessOptions::from_args(args(&["--headless", "out.png", "--golden", "golden"])).unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected argument '--golden'.");
+    }
+}
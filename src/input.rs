@@ -0,0 +1,334 @@
+use nalgebra_glm as glm;
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode};
+
+/// The number of key codes tracked per keyboard row, like Shadertoy's 256 pixel wide keyboard texture.
+pub const KEY_COUNT: usize = 256;
+
+/// The mouse and keyboard state forwarded to the shaders.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct InputState {
+    pub mouse: MouseState,
+    pub keyboard: KeyboardState,
+}
+
+impl InputState {
+    /// Called once the frame's uniforms were written, clears everything that is only reported for one frame.
+    pub fn end_frame(&mut self) {
+        self.mouse.end_frame();
+        self.keyboard.end_frame();
+    }
+}
+
+/// Tracks the mouse the way Shadertoy reports it in `iMouse`, in pixels with the origin at the bottom left.
+///
+/// xy is the cursor position while the left button is held (the last held position otherwise). zw is the
+/// position of the last click, z is negated once the button is released and w is only positive during the
+/// frame the click happened in.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MouseState {
+    cursor: glm::Vec2,
+    buttons: u32,
+    clicked: bool,
+    position: glm::Vec2,
+    click: glm::Vec2,
+}
+
+impl MouseState {
+    pub fn cursor_moved(&mut self, position: PhysicalPosition<f64>, window_height: u32) {
+        self.cursor = glm::vec2(position.x as f32, window_height as f32 - position.y as f32);
+
+        if self.is_pressed(MouseButton::Left) {
+            self.position = self.cursor;
+        }
+    }
+
+    pub fn button(&mut self, button: MouseButton, state: ElementState) {
+        let mask = button_mask(button);
+
+        match state {
+            ElementState::Pressed => self.buttons |= mask,
+            ElementState::Released => self.buttons &= !mask,
+        }
+
+        if button == MouseButton::Left && state == ElementState::Pressed {
+            self.clicked = true;
+            self.position = self.cursor;
+            self.click = self.cursor;
+        }
+    }
+
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        self.buttons & button_mask(button) != 0
+    }
+
+    /// The current cursor position, `iCursor`, updated whether or not a button is held.
+    pub fn cursor(&self) -> glm::Vec2 {
+        self.cursor
+    }
+
+    /// The held buttons, `iMouseButtons`, bit 0 is left, 1 right, 2 middle and higher bits other buttons.
+    pub fn buttons(&self) -> u32 {
+        self.buttons
+    }
+
+    /// The `iMouse` value for the current frame.
+    pub fn uniform(&self) -> glm::Vec4 {
+        let z = if self.is_pressed(MouseButton::Left) { self.click.x } else { -self.click.x };
+        let w = if self.clicked { self.click.y } else { -self.click.y };
+
+        glm::vec4(self.position.x, self.position.y, z, w)
+    }
+
+    fn end_frame(&mut self) {
+        self.clicked = false;
+    }
+}
+
+fn button_mask(button: MouseButton) -> u32 {
+    match button {
+        MouseButton::Left => 1,
+        MouseButton::Right => 1 << 1,
+        MouseButton::Middle => 1 << 2,
+        MouseButton::Other(index) => 1u32.checked_shl(3 + index as u32).unwrap_or(0),
+    }
+}
+
+/// Tracks keys by their JavaScript key code in three rows, like Shadertoy's keyboard texture: keys held down,
+/// keys pressed during this frame and keys toggled by each press.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct KeyboardState {
+    down: [u32; KEY_COUNT / 32],
+    pressed: [u32; KEY_COUNT / 32],
+    toggled: [u32; KEY_COUNT / 32],
+}
+
+impl KeyboardState {
+    pub fn key(&mut self, input: &KeyboardInput) {
+        let Some(code) = input.virtual_keycode.and_then(key_code) else {
+            return;
+        };
+
+        let (word, bit) = (code / 32, 1 << (code % 32));
+        match input.state {
+            // Key repeat sends presses without releases in between, only the first one counts.
+            ElementState::Pressed if self.down[word] & bit == 0 => {
+                self.down[word] |= bit;
+                self.pressed[word] |= bit;
+                self.toggled[word] ^= bit;
+            }
+            ElementState::Pressed => {}
+            ElementState::Released => self.down[word] &= !bit,
+        }
+    }
+
+    pub fn is_down(&self, key: VirtualKeyCode) -> bool {
+        key_code(key).is_some_and(|code| self.down[code / 32] & (1 << (code % 32)) != 0)
+    }
+
+    /// The `iKeyboard` value, the down, pressed and toggled rows one after another as 256 bits each.
+    pub fn uniform(&self) -> [[u32; 4]; 6] {
+        let mut words = [0; 24];
+        words[0..8].copy_from_slice(&self.down);
+        words[8..16].copy_from_slice(&self.pressed);
+        words[16..24].copy_from_slice(&self.toggled);
+
+        let mut uniform = [[0; 4]; 6];
+        for (vector, chunk) in uniform.iter_mut().zip(words.chunks_exact(4)) {
+            vector.copy_from_slice(chunk);
+        }
+
+        uniform
+    }
+
+    fn end_frame(&mut self) {
+        self.pressed = [0; KEY_COUNT / 32];
+    }
+}
+
+/// The JavaScript key code Shadertoy shaders use for a key.
+fn key_code(key: VirtualKeyCode) -> Option<usize> {
+    use VirtualKeyCode::*;
+
+    let code = match key {
+        Back => 8,
+        Tab => 9,
+        Return | NumpadEnter => 13,
+        LShift | RShift => 16,
+        LControl | RControl => 17,
+        LAlt | RAlt => 18,
+        Pause => 19,
+        Capital => 20,
+        Escape => 27,
+        Space => 32,
+        PageUp => 33,
+        PageDown => 34,
+        End => 35,
+        Home => 36,
+        Left => 37,
+        Up => 38,
+        Right => 39,
+        Down => 40,
+        Insert => 45,
+        Delete => 46,
+        Key0 => 48,
+        Key1 => 49,
+        Key2 => 50,
+        Key3 => 51,
+        Key4 => 52,
+        Key5 => 53,
+        Key6 => 54,
+        Key7 => 55,
+        Key8 => 56,
+        Key9 => 57,
+        A => 65,
+        B => 66,
+        C => 67,
+        D => 68,
+        E => 69,
+        F => 70,
+        G => 71,
+        H => 72,
+        I => 73,
+        J => 74,
+        K => 75,
+        L => 76,
+        M => 77,
+        N => 78,
+        O => 79,
+        P => 80,
+        Q => 81,
+        R => 82,
+        S => 83,
+        T => 84,
+        U => 85,
+        V => 86,
+        W => 87,
+        X => 88,
+        Y => 89,
+        Z => 90,
+        Numpad0 => 96,
+        Numpad1 => 97,
+        Numpad2 => 98,
+        Numpad3 => 99,
+        Numpad4 => 100,
+        Numpad5 => 101,
+        Numpad6 => 102,
+        Numpad7 => 103,
+        Numpad8 => 104,
+        Numpad9 => 105,
+        NumpadMultiply => 106,
+        NumpadAdd => 107,
+        NumpadSubtract => 109,
+        NumpadDecimal => 110,
+        NumpadDivide => 111,
+        F1 => 112,
+        F2 => 113,
+        F3 => 114,
+        F4 => 115,
+        F5 => 116,
+        F6 => 117,
+        F7 => 118,
+        F8 => 119,
+        F9 => 120,
+        F10 => 121,
+        F11 => 122,
+        F12 => 123,
+        Semicolon => 186,
+        Equals => 187,
+        Comma => 188,
+        Minus => 189,
+        Period => 190,
+        Slash => 191,
+        Grave => 192,
+        LBracket => 219,
+        Backslash => 220,
+        RBracket => 221,
+        Apostrophe => 222,
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(mouse: &mut MouseState, x: f64, y: f64) {
+        mouse.cursor_moved(PhysicalPosition::new(x, y), 100);
+        mouse.button(MouseButton::Left, ElementState::Pressed);
+    }
+
+    #[allow(deprecated)]
+    fn key(state: ElementState, key: VirtualKeyCode) -> KeyboardInput {
+        KeyboardInput { scancode: 0, state, virtual_keycode: Some(key), modifiers: Default::default() }
+    }
+
+    #[test]
+    fn click_position_is_positive_while_held_and_during_the_click_frame() {
+        let mut mouse = MouseState::default();
+        press(&mut mouse, 10.0, 30.0);
+        assert_eq!(mouse.uniform(), glm::vec4(10.0, 70.0, 10.0, 70.0));
+
+        mouse.end_frame();
+        mouse.cursor_moved(PhysicalPosition::new(20.0, 40.0), 100);
+        assert_eq!(mouse.uniform(), glm::vec4(20.0, 60.0, 10.0, -70.0));
+    }
+
+    #[test]
+    fn click_position_goes_negative_once_released() {
+        let mut mouse = MouseState::default();
+        press(&mut mouse, 10.0, 30.0);
+        mouse.end_frame();
+        mouse.button(MouseButton::Left, ElementState::Released);
+
+        // The cursor no longer moves xy without the button held.
+        mouse.cursor_moved(PhysicalPosition::new(50.0, 50.0), 100);
+        assert_eq!(mouse.uniform(), glm::vec4(10.0, 70.0, -10.0, -70.0));
+        assert_eq!(mouse.cursor(), glm::vec2(50.0, 50.0));
+        assert_eq!(mouse.buttons(), 0);
+    }
+
+    #[test]
+    fn buttons_have_a_bit_each() {
+        let mut mouse = MouseState::default();
+        mouse.button(MouseButton::Right, ElementState::Pressed);
+        mouse.button(MouseButton::Other(1), ElementState::Pressed);
+        assert_eq!(mouse.buttons(), 0b10010);
+
+        mouse.button(MouseButton::Other(100), ElementState::Pressed);
+        assert_eq!(mouse.buttons(), 0b10010);
+    }
+
+    #[test]
+    fn keys_set_the_down_pressed_and_toggled_rows() {
+        let mut keyboard = KeyboardState::default();
+        let (word, bit) = (65 / 32, 1 << (65 % 32));
+
+        keyboard.key(&key(ElementState::Pressed, VirtualKeyCode::A));
+        let uniform = keyboard.uniform();
+        assert!(keyboard.is_down(VirtualKeyCode::A));
+        assert_eq!(uniform[word / 4][word % 4], bit);
+        assert_eq!(uniform[2 + word / 4][word % 4], bit);
+        assert_eq!(uniform[4 + word / 4][word % 4], bit);
+
+        // Pressed only lasts a frame and key repeat doesn't press again.
+        keyboard.end_frame();
+        keyboard.key(&key(ElementState::Pressed, VirtualKeyCode::A));
+        let uniform = keyboard.uniform();
+        assert_eq!(uniform[word / 4][word % 4], bit);
+        assert_eq!(uniform[2 + word / 4][word % 4], 0);
+        assert_eq!(uniform[4 + word / 4][word % 4], bit);
+
+        // Releasing keeps the toggle, the next press clears it.
+        keyboard.key(&key(ElementState::Released, VirtualKeyCode::A));
+        assert!(!keyboard.is_down(VirtualKeyCode::A));
+        assert_eq!(keyboard.uniform()[4 + word / 4][word % 4], bit);
+
+        keyboard.key(&key(ElementState::Pressed, VirtualKeyCode::A));
+        let uniform = keyboard.uniform();
+        assert_eq!(uniform[2 + word / 4][word % 4], bit);
+        assert_eq!(uniform[4 + word / 4][word % 4], 0);
+    }
+}
@@ -0,0 +1,1418 @@
+//! A Vulkan renderer for Shadertoy style fragment shaders and scenes made of several passes.
+//!
+//! `run` opens a window and drives an `Engine` from its events, `Headless` renders offscreen. See `examples/`.
+//!
+//! Both own their Vulkan objects and need no `unsafe` to use. Raw Vulkan access goes through `raw` and `DrawHook`,
+//! where the calls are `unsafe` as usual.
+//!
+//! Their methods fail with an `EngineError`, which tells apart the failures a caller can recover from.
+
+#![allow(
+    dead_code,
+    unused_variables,
+    clippy::too_many_arguments,
+    clippy::unnecessary_wraps,
+    clippy::missing_safety_doc
+)]
+
+const VALIDATION_LAYER: vk::ExtensionName =
+    vk::ExtensionName::from_bytes(b"VK_LAYER_KHRONOS_validation");
+
+const DEVICE_EXTENSIONS: &[vk::ExtensionName] = &[vk::KHR_SWAPCHAIN_EXTENSION.name];
+
+    
+use std::ptr::copy_nonoverlapping as memcpy;
+use std::time::Instant;
+use std::mem::size_of;
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::os::raw::c_void;
+use std::mem;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use buffers::common::create_buffer;
+use buffers::upload::TransferQueue;
+use vulkanalia::loader::{LibloadingLoader, LIBRARY};
+use vulkanalia::window as vk_window;
+use vulkanalia::prelude::v1_1::*;
+use vulkanalia::vk::ExtDebugUtilsExtension;
+use vulkanalia::vk::KhrSwapchainExtension;
+use winit::dpi::LogicalSize;
+use winit::event::{Event, WindowEvent, VirtualKeyCode, ElementState};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Window, WindowBuilder};
+
+use log::*;
+
+mod vertex;
+
+mod mesh;
+use mesh::*;
+
+mod uniform_buffer_object;
+use crate::uniform_buffer_object::*;
+
+mod push_constants;
+pub use crate::push_constants::PushConstants;
+
+mod frame;
+use crate::frame::*;
+pub use crate::frame::{DrawContext, DrawHook, RawHandles};
+
+mod input;
+pub use crate::input::{InputState, KeyboardState, MouseState};
+
+mod create_renderpass;
+use crate::create_renderpass::*;
+
+mod create_descriptor_sets;
+use create_descriptor_sets::*;
+
+mod error;
+pub use crate::error::{EngineError, SuitabilityError};
+
+mod queue_family_indices;
+use queue_family_indices::*;
+
+mod swapchain_support;
+use swapchain_support::*;
+
+mod create_swapchain;
+use create_swapchain::*;
+
+mod create_framebuffers;
+use create_framebuffers::*;
+
+mod resources;
+use resources::*;
+
+mod allocator;
+use allocator::*;
+
+mod buffers;
+
+mod create_pipeline;
+use create_pipeline::*;
+
+mod pipeline_cache;
+use pipeline_cache::*;
+
+mod shader_manager;
+pub use shader_manager::{ShaderError, ShaderManager};
+
+mod create_image;
+use create_image::*;
+
+mod create_depth_objects;
+use create_depth_objects::*;
+
+mod create_color_objects;
+use create_color_objects::*;
+pub use create_color_objects::next_msaa_samples;
+
+mod single_time_commands;
+
+mod texture;
+
+mod render_graph;
+use render_graph::*;
+
+mod headless;
+pub use headless::{render_headless, write_png, Headless, HeadlessOptions};
+
+mod golden;
+pub use golden::{run_golden_tests, GoldenOptions};
+
+mod config;
+pub use config::{Config, CONFIG_FILE};
+
+/// Opens a window set up by `config` and renders its shader or scene until the window is closed, with the
+/// `draw_hooks` recording extra draw calls every frame.
+///
+/// Escape exits, R reloads the shaders and M cycles through the MSAA sample counts.
+pub fn run(config: &Config, draw_hooks: Vec<Box<dyn DrawHook>>) -> Result<()> {
+    let shader_manager = config.shader_manager()?;
+
+    // Window
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title(&config.title)
+        .with_inner_size(LogicalSize::new(config.size.width, config.size.height))
+        .build(&event_loop)?;
+
+    // Engine
+
+    // Dropped when the loop ends, the event loop never returns so it can't go out of scope.
+    let mut running = Some(Engine::create(window, config, shader_manager, draw_hooks)?);
+    let mut minimized = false;
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+
+        let Some(engine) = running.as_mut() else {
+            return;
+        };
+
+        match event {
+            // Render a frame if the window is not minimized.
+            Event::MainEventsCleared if !minimized => match engine.render() {
+                Ok(()) => {}
+                // The window changed between acquiring and presenting, the next frame catches up.
+                Err(EngineError::SwapchainOutOfDate) => engine.resize(),
+                Err(error) => handle_error(error, control_flow),
+            },
+
+            Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } => {
+                debug!("This input event was recorded: {:#?}, the scancode is {}", input, input.scancode);
+                engine.input().keyboard.key(&input);
+
+                if input.state == ElementState::Released {
+                    match input.virtual_keycode {
+                        Some(VirtualKeyCode::Escape) => control_flow.set_exit(),
+                        Some(VirtualKeyCode::R) => {
+                            if let Err(error) = engine.reload_shader() {
+                                handle_error(error, control_flow);
+                            }
+                        }
+                        Some(VirtualKeyCode::M) => {
+                            let samples = next_msaa_samples(engine.msaa_samples(), engine.max_msaa_samples());
+                            if let Err(error) = engine.set_msaa_samples(samples) {
+                                handle_error(error, control_flow);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            },
+
+            Event::WindowEvent { event: WindowEvent::CursorMoved { position, .. }, .. } =>
+            {
+                let height = engine.window().inner_size().height;
+                engine.input().mouse.cursor_moved(position, height);
+            }
+
+            Event::WindowEvent { event: WindowEvent::MouseInput { state, button, .. }, .. } =>
+                engine.input().mouse.button(button, state),
+
+            // Window is resized and swapchain needs to be recreated. If the window is minimized, rendering will seize.
+            Event::WindowEvent { event: WindowEvent::Resized(size), .. } =>
+            {
+                if size.width == 0 && size.height == 0 {
+                    minimized = true;
+                } else {
+                    minimized = false;
+                    engine.resize();
+                }
+            }
+            
+            // Destroy the engine.
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                *control_flow = ControlFlow::Exit;
+                running = None;
+            }
+
+            // Escape exits without a close request.
+            Event::LoopDestroyed => running = None,
+            _ => {}
+        }
+    });
+}
+
+/// Reports an error of the engine. Shader errors are already shown in the title while the previous pipelines keep
+/// drawing, anything else ends the event loop, which drops the engine.
+fn handle_error(error: EngineError, control_flow: &mut ControlFlow) {
+    if let EngineError::Shader(_) = error {
+        return;
+    }
+
+    error!("Exiting after an engine error: {}", error);
+    control_flow.set_exit_with_code(1);
+}
+
+/// Creates the Vulkan instance. Without a window (headless rendering) no surface extensions are enabled, with
+/// `data.validation` the validation layer and its messenger are. If the layer isn't installed `data.validation` is
+/// turned off and the instance is created without it.
+unsafe fn create_instance(
+    window: Option<&Window>,
+    entry: Entry,
+    data: &mut AppData)
+-> Result<OwnedInstance> {
+
+    let available_layers = entry
+        .enumerate_instance_layer_properties()?
+        .iter()
+        .map(|l| l.layer_name)
+        .collect::<HashSet<_>>();
+
+    if data.validation && !available_layers.contains(&VALIDATION_LAYER) {
+        warn!("Validation layer requested but not supported, continuing without it. Have you set env 'VK_LOADER_DEBUG' to 'all'?");
+        data.validation = false;
+    }
+
+    let layers = if data.validation {
+        vec![VALIDATION_LAYER.as_ptr()]
+    } else {
+        Vec::new()
+    };
+
+    let application_info = vk::ApplicationInfo::builder()
+        .application_name(b"Vulkan Tutorial\0")
+        .application_version(vk::make_version(1, 0, 0))
+        .engine_name(b"No Engine\0")
+        .engine_version(vk::make_version(1, 0, 0))
+        .api_version(vk::make_version(1, 1, 0));
+
+    let available_extensions = entry
+        .enumerate_instance_extension_properties(None)?
+        .iter()
+        .map(|e| e.extension_name)
+        .collect::<HashSet<_>>();
+
+    let window_extensions = match window {
+        Some(window) => vk_window::get_required_instance_extensions(window),
+        None => &[],
+    };
+
+    let missing = window_extensions
+        .iter()
+        .filter(|e| !available_extensions.contains(**e))
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        return Err(EngineError::MissingExtensions(missing).into());
+    }
+
+    let mut extensions = window_extensions.iter().map(|e| e.as_ptr()).collect::<Vec<_>>();
+
+    if data.validation {
+        extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION.name.as_ptr());
+    }
+
+    for ele in &extensions {
+        debug!("Extension '{:?}' required for instance.", *ele);
+    }
+
+    let mut info = vk::InstanceCreateInfo::builder()
+        .application_info(&application_info)
+        .enabled_layer_names(&layers)
+        .enabled_extension_names(&extensions);
+
+    let mut debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+        .message_severity(vk::DebugUtilsMessageSeverityFlagsEXT::all())
+        .message_type(vk::DebugUtilsMessageTypeFlagsEXT::all())
+        .user_callback(Some(debug_callback));
+    
+    if data.validation {
+        info = info.push_next(&mut debug_info);
+    }
+
+    let mut instance = OwnedInstance::new(entry.clone(), entry.create_instance(&info, None)?);
+
+    if data.validation {
+        instance.messenger = instance.create_debug_utils_messenger_ext(&debug_info, None)?;
+    }
+
+    Ok(instance)
+}
+
+/// Picks a physical device and creates the logical device with everything rendering to `window` needs: the
+/// swapchain, render targets, pipelines, buffers and the objects of `frames_in_flight` frames. `data.msaa_samples`
+/// is the requested sample count, clamped to what the device supports.
+unsafe fn create_device_objects(
+    window: &Window,
+    instance: &SharedInstance,
+    shader_manager: &ShaderManager,
+    data: &mut AppData,
+    frames_in_flight: usize,
+) -> Result<SharedDevice> {
+    pick_physical_device(instance, data)?;
+
+    let device = create_logical_device(instance, data)?;
+    data.allocator = MemoryAllocator::new(instance, &device, data.physical_device);
+
+    let swapchain_data = create_swapchain(window, instance, &device, &CreateSwapchainData {
+        surface: data.surface,
+        physical_device: data.physical_device,
+        present_mode: data.present_mode,
+    })?;
+    data.swapchain = swapchain_data.swapchain;
+    data.swapchain_extent = swapchain_data.swapchain_extent;
+    data.swapchain_format = swapchain_data.swapchain_format;
+    data.swapchain_images = swapchain_data.swapchain_images;
+
+    create_swapchain_image_views(&device, data)?;
+
+    data.depth_format = get_depth_format(instance, &data.physical_device)?;
+    data.max_msaa_samples = get_max_msaa_samples(instance, &data.physical_device);
+    data.msaa_samples = clamp_msaa_samples(data.msaa_samples, data.max_msaa_samples);
+    create_render_targets(&device, data)?;
+
+    create_render_pass(instance, &device, &data.swapchain_format, data.depth_format, data.msaa_samples, vk::ImageLayout::PRESENT_SRC_KHR, &mut data.render_pass)?;
+    data.descriptor_set_layout = create_descriptor_set_layout(&device)?;
+    create_command_pool(instance, &device, data)?;
+    data.pipeline_cache = create_pipeline_cache(instance, &device, data.physical_device, &pipeline_cache_path())?;
+
+    create_render_graph(instance, &device, shader_manager, data)?;
+
+    create_framebuffers(&device, &data.swapchain_image_views, *data.color_image_view, *data.depth_image_view, &data.render_pass, &data.swapchain_extent, &mut data.framebuffers)?;
+    create_uniform_buffers(data)?;
+    create_pass_descriptor_sets(&device, data)?;
+
+    data.quad = Mesh::quad(&device, &data.allocator, &data.transfer)?;
+
+    create_frame_commands(instance, &device, data, frames_in_flight)?;
+
+    create_sync_objects(&device, data, frames_in_flight)?;
+
+    info!("Device memory: {}", data.allocator.stats());
+
+    Ok(device)
+}
+
+/// Renders a fragment shader or scene to a window, see `run` for the event loop driving it.
+///
+/// The engine owns the window and every Vulkan object it creates and keeps them in sync with the frames in
+/// flight, so driving it needs no `unsafe`. `raw` gives access to the underlying handles.
+#[derive(Debug)]
+pub struct Engine {
+    instance: SharedInstance,
+    device: SharedDevice,
+    data: AppData,
+    frame: usize,
+    start: Instant,
+    /// The window title, shader errors are shown after it.
+    title: String,
+    resized: bool,
+    /// Set when recreating a lost device failed, the engine has no device to render with from then on.
+    device_lost: bool,
+
+    shader_manager: ShaderManager,
+
+    /// Frames rendered so far, `iFrame`.
+    frames_rendered: u32,
+    last_frame: Instant,
+    input: InputState,
+    /// Scene code recording extra draw calls into the image pass of every frame.
+    draw_hooks: Vec<Box<dyn DrawHook>>,
+    /// Dropped last, the surface was created from it.
+    window: Window,
+}
+
+impl Engine {
+    /// Creates the Vulkan objects for `window`, set up by `config` and drawing what `shader_manager` loads.
+    pub fn create(
+        window: Window,
+        config: &Config,
+        shader_manager: ShaderManager,
+        draw_hooks: Vec<Box<dyn DrawHook>>,
+    ) -> Result<Self, EngineError> {
+        // The surface and everything else is created from the window the engine takes ownership of.
+        unsafe { Ok(Self::create_objects(window, config, shader_manager, draw_hooks)?) }
+    }
+
+    unsafe fn create_objects(
+        window: Window,
+        config: &Config,
+        mut shader_manager: ShaderManager,
+        draw_hooks: Vec<Box<dyn DrawHook>>,
+    ) -> Result<Self> {
+        let loader = LibloadingLoader::new(LIBRARY)?;
+        let entry = Entry::new(loader).map_err(|b| anyhow!("{}", b))?;
+
+        let mut data = AppData {
+            validation: config.validation,
+            present_mode: config.present_mode,
+            msaa_samples: config.msaa_samples,
+            ..Default::default()
+        };
+        let mut instance = create_instance(Some(&window), entry, &mut data)?;
+
+        instance.surface = vk_window::create_surface(&instance, &window)?;
+        data.surface = instance.surface;
+        let instance = Rc::new(instance);
+
+        if let Err(error) = shader_manager.watch() {
+            warn!("Shader hot-reload is unavailable, press R to reload: {}", error);
+        }
+
+        let device = create_device_objects(&window, &instance, &shader_manager, &mut data, config.frames_in_flight)?;
+
+        let mut engine = Self {
+            instance,
+            device,
+            data,
+            frame: 0,
+            start: Instant::now(),
+            title: config.title.clone(),
+            resized: false,
+            device_lost: false,
+            shader_manager,
+            frames_rendered: 0,
+            last_frame: Instant::now(),
+            input: InputState::default(),
+            draw_hooks,
+            window,
+        };
+        engine.upload_assets()?;
+
+        Ok(engine)
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    /// The Vulkan handles behind the engine, see `RawHandles`.
+    pub fn raw(&self) -> RawHandles<'_> {
+        RawHandles {
+            instance: &self.instance,
+            device: &self.device,
+            physical_device: self.data.physical_device,
+            graphics_queue: self.data.graphics_queue,
+            command_pool: *self.data.command_pool,
+            render_pass: *self.data.render_pass,
+            descriptor_set_layout: *self.data.descriptor_set_layout,
+            pipeline_cache: *self.data.pipeline_cache,
+            msaa_samples: self.data.msaa_samples,
+            extent: self.data.swapchain_extent,
+        }
+    }
+
+    /// Marks the swapchain as outdated after the window was resized, it's recreated before the next frame.
+    pub fn resize(&mut self) {
+        self.resized = true;
+    }
+
+    /// The mouse and keyboard state passed to the shaders, to be fed with the window's input events.
+    pub fn input(&mut self) -> &mut InputState {
+        &mut self.input
+    }
+
+    pub fn msaa_samples(&self) -> vk::SampleCountFlags {
+        self.data.msaa_samples
+    }
+
+    /// The highest MSAA sample count the device supports.
+    pub fn max_msaa_samples(&self) -> vk::SampleCountFlags {
+        self.data.max_msaa_samples
+    }
+
+    /// Renders a frame. When the device is lost or runs out of memory the engine recreates it and everything
+    /// rendering needs instead of failing, see `DrawHook::upload_assets`. If that fails too every later call fails
+    /// with `DeviceLost`.
+    pub fn render(&mut self) -> Result<(), EngineError> {
+        self.check_device()?;
+
+        // A shader that fails to compile while hot-reloading is shown in the title, the previous pipelines keep
+        // drawing.
+        if self.shader_manager.poll_changes() {
+            match self.reload_shader() {
+                Ok(()) | Err(EngineError::Shader(_)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        // Each frame waits for the fence of the previous frame in its slot before reusing its objects.
+        match unsafe { self.render_frame() }.map_err(EngineError::from) {
+            Err(EngineError::DeviceLost) => {
+                warn!("The device was lost, recreating it");
+                unsafe { Ok(self.recreate_device()?) }
+            }
+            // The failed frame may have left its fence or semaphores unusable, so everything is recreated after
+            // the resources waiting for deletion are freed.
+            Err(error @ EngineError::OutOfMemory { .. }) => {
+                warn!("{} Recreating the device", error);
+                unsafe { Ok(self.recreate_device()?) }
+            }
+            result => result,
+        }
+    }
+
+    /// Fails with `DeviceLost` once the engine has no device left to use.
+    fn check_device(&self) -> Result<(), EngineError> {
+        if self.device_lost {
+            Err(EngineError::DeviceLost)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Tears down the device and every object created from it, then creates them again for the same window,
+    /// shaders and settings. The frame count, time and inputs carry on, draw hooks upload their assets again. Unless
+    /// the device was lost, the pipeline cache is saved first so the new device can start from it.
+    ///
+    /// The new objects replace the old ones only once they're all created. Until then, and for good if that
+    /// fails, the engine has no swapchain and `device_lost` is set.
+    unsafe fn recreate_device(&mut self) -> Result<()> {
+        // A lost device finishes its work on its own, waiting fails but its objects can be destroyed regardless.
+        match self.device.device_wait_idle() {
+            Ok(()) => {
+                if let Err(error) = save_pipeline_cache(&self.device, *self.data.pipeline_cache, &pipeline_cache_path()) {
+                    warn!("Failed to save the pipeline cache: {}", error);
+                }
+            }
+            Err(error) => warn!("Failed to wait for the device before recreating it: {}", error),
+        }
+
+        self.device_lost = true;
+        self.data.deletion_queue.flush();
+        self.destroy_assets();
+
+        // The surface only takes a new swapchain once the previous one is destroyed.
+        self.destroy_swapchain();
+
+        let mut data = AppData {
+            validation: self.data.validation,
+            surface: self.data.surface,
+            present_mode: self.data.present_mode,
+            msaa_samples: self.data.msaa_samples,
+            ..Default::default()
+        };
+        let frames_in_flight = self.data.frames.len();
+        let device = create_device_objects(&self.window, &self.instance, &self.shader_manager, &mut data, frames_in_flight)?;
+
+        // The old objects keep the old device alive until the last of them is dropped here.
+        self.data = data;
+        self.device = device;
+        self.frame = 0;
+        self.resized = false;
+
+        self.upload_assets()?;
+        self.device_lost = false;
+
+        Ok(())
+    }
+
+    /// Lets the draw hooks create their objects for the current device.
+    unsafe fn upload_assets(&mut self) -> Result<()> {
+        let mut draw_hooks = mem::take(&mut self.draw_hooks);
+        let result = draw_hooks.iter_mut().try_for_each(|hook| hook.upload_assets(self.raw()));
+        self.draw_hooks = draw_hooks;
+
+        result
+    }
+
+    /// Lets the draw hooks destroy their objects before the device goes away, no frame may be in flight.
+    unsafe fn destroy_assets(&mut self) {
+        let mut draw_hooks = mem::take(&mut self.draw_hooks);
+        draw_hooks.iter_mut().for_each(|hook| hook.destroy_assets(self.raw()));
+        self.draw_hooks = draw_hooks;
+    }
+
+    unsafe fn render_frame(&mut self) -> Result<()> {
+        self.device.wait_for_fences(
+            &[*self.data.in_flight_fences[self.frame]],
+            true,
+            u64::MAX,
+        )?;
+
+        // Every frame but the ones still in flight in the other slots has completed.
+        let in_flight = self.data.frames.len() as u64 - 1;
+        self.data.deletion_queue.collect((self.frames_rendered as u64).saturating_sub(in_flight));
+    
+        let result = self
+            .device
+            .acquire_next_image_khr(
+                *self.data.swapchain,
+                u64::MAX,
+                *self.data.image_available_semaphores[self.frame],
+                vk::Fence::null(),
+        );
+
+        let image_index = match result {
+            Ok((image_index, _)) => image_index as usize,
+            Err(vk::ErrorCode::OUT_OF_DATE_KHR) => return self.recreate_swapchain(),
+            Err(e) => return Err(anyhow!(e)),
+        };
+        
+        if !self.data.images_in_flight[image_index as usize].is_null() {
+            self.device.wait_for_fences(
+                &[self.data.images_in_flight[image_index as usize]],
+                true,
+                u64::MAX,
+            )?;
+        }
+    
+        self.data.images_in_flight[image_index as usize] =
+            *self.data.in_flight_fences[self.frame];
+
+        let push_constants = self.update_inputs(image_index)?;
+
+        // Waiting for the frame's fence above also means its previous commands are done and can be reset.
+        let command_buffer = self.data.frames[self.frame].begin(&self.device)?;
+        record_command_buffer(
+            &self.device,
+            &self.data,
+            command_buffer,
+            image_index,
+            &push_constants,
+            &mut self.draw_hooks,
+        )?;
+
+        let wait_semaphores = &[*self.data.image_available_semaphores[self.frame]];
+        let wait_stages = &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let command_buffers = &[command_buffer];
+        let signal_semaphores = &[*self.data.render_finished_semaphores[self.frame]];
+        let submit_info = vk::SubmitInfo::builder()
+            .wait_semaphores(wait_semaphores)
+            .wait_dst_stage_mask(wait_stages)
+            .command_buffers(command_buffers)
+            .signal_semaphores(signal_semaphores);
+
+        self.device.reset_fences(&[*self.data.in_flight_fences[self.frame]])?;
+
+        self.device.queue_submit(self.data.graphics_queue, &[submit_info], *self.data.in_flight_fences[self.frame])?;
+
+        let swapchains = &[*self.data.swapchain];
+        let image_indices = &[image_index as u32];
+        let present_info = vk::PresentInfoKHR::builder()
+            .wait_semaphores(signal_semaphores)
+            .swapchains(swapchains)
+            .image_indices(image_indices);
+
+        let result = self.device.queue_present_khr(self.data.present_queue, &present_info);
+
+        let changed = result == Ok(vk::SuccessCode::SUBOPTIMAL_KHR)
+            || result == Err(vk::ErrorCode::OUT_OF_DATE_KHR);
+        
+        if self.resized || changed {
+            self.resized = false;
+            self.recreate_swapchain()?;
+        } else if let Err(e) = result {
+            return Err(anyhow!(e));
+        }
+
+        self.frame = (self.frame + 1) % self.data.frames.len();
+
+        Ok(())
+    }
+
+    /// Switches the image pass to another MSAA sample count, which needs a new render pass, pipelines and render
+    /// targets.
+    pub fn set_msaa_samples(&mut self, samples: vk::SampleCountFlags) -> Result<(), EngineError> {
+        self.check_device()?;
+
+        if samples == self.data.msaa_samples {
+            return Ok(());
+        }
+
+        info!("Switching to {:?} MSAA samples", samples);
+
+        // The render pass is only replaced once the device is idle.
+        unsafe {
+            self.device.device_wait_idle()?;
+
+            let previous = self.data.msaa_samples;
+            self.data.msaa_samples = samples;
+
+            // Hook objects may depend on the render pass and sample count, so hooks rebuild them with it.
+            self.destroy_assets();
+            let result = self.recreate_render_pass().and_then(|_| self.replace_pipeline());
+
+            // The previous pipelines are kept when the new ones fail to build, so keep drawing with their sample
+            // count.
+            if let Err(error) = result {
+                self.data.msaa_samples = previous;
+                let restored = self.recreate_render_pass();
+                self.upload_assets()?;
+                restored?;
+                return Err(error.into());
+            }
+
+            self.upload_assets()?;
+
+            Ok(self.recreate_swapchain()?)
+        }
+    }
+
+    /// Replaces the render pass with one for the current swapchain format and sample count, the device must be idle.
+    unsafe fn recreate_render_pass(&mut self) -> Result<()> {
+        create_render_pass(&self.instance, &self.device, &self.data.swapchain_format, self.data.depth_format, self.data.msaa_samples, vk::ImageLayout::PRESENT_SRC_KHR, &mut self.data.render_pass)
+    }
+
+    /// Rebuilds only the graphics pipeline from the current shader sources, keeping the previous pipeline
+    /// if they fail to compile. The compile error is returned as `EngineError::Shader`.
+    pub fn reload_shader(&mut self) -> Result<(), EngineError> {
+        self.check_device()?;
+        info!("Reloading shader");
+
+        // The command buffers are recorded every frame, so the next one binds the new pipeline. The previous
+        // pipelines are deferred until the frames using them completed.
+        unsafe { Ok(self.replace_pipeline()?) }
+    }
+
+    /// Builds the pipelines of every pass from the current shaders and swaps them in for the previous ones, which
+    /// are dropped once the frames in flight are done with them. If the build fails the error is shown in the
+    /// title and returned, the previous pipelines stay in use.
+    unsafe fn replace_pipeline(&mut self) -> Result<()> {
+        let result = self.data.render_graph.create_pipelines(
+            &self.device,
+            *self.data.pipeline_cache,
+            &self.shader_manager,
+            &self.data.descriptor_set_layout,
+            &self.data.render_pass,
+            self.data.msaa_samples,
+        );
+
+        match result {
+            Ok(pipelines) => {
+                let image = mem::replace(&mut self.data.pipeline, pipelines.image.1);
+                let image_layout = mem::replace(&mut self.data.pipeline_layout, pipelines.image.0);
+                let buffers = mem::replace(&mut self.data.render_graph.pipelines, pipelines.buffers);
+
+                let submitted = self.frames_rendered as u64;
+                self.data.deletion_queue.defer(submitted, (image_layout, image));
+                self.data.deletion_queue.defer(submitted, buffers);
+
+                self.window.set_title(&self.title);
+                Ok(())
+            }
+            Err(error) => {
+                error!("Failed to build the pipeline, keeping the previous one:\n{:#}", error);
+
+                let summary = match error.downcast_ref::<ShaderError>() {
+                    Some(error) => error.summary(),
+                    None => error.to_string(),
+                };
+                self.window.set_title(&format!("{} - shader error: {}", self.title, summary));
+                Err(error)
+            }
+        }
+    }
+
+    unsafe fn recreate_swapchain(&mut self) -> Result<()> {
+        self.device.device_wait_idle()?;
+
+        self.data.deletion_queue.flush();
+        self.destroy_swapchain();
+
+        let previous_format = self.data.swapchain_format;
+        let swapchain_create_data = CreateSwapchainData{
+            surface: self.data.surface,
+            physical_device: self.data.physical_device,
+            present_mode: self.data.present_mode,
+        };
+
+        let swapchain_data = create_swapchain(&self.window, &self.instance, &self.device, &swapchain_create_data)?;
+        
+        self.data.swapchain = swapchain_data.swapchain;
+        self.data.swapchain_extent = swapchain_data.swapchain_extent;
+        self.data.swapchain_format = swapchain_data.swapchain_format;
+        self.data.swapchain_images = swapchain_data.swapchain_images;
+
+        // The viewport and scissor are dynamic, so the render pass and pipelines only depend on the format, which
+        // rarely changes with a resize.
+        if self.data.swapchain_format != previous_format {
+            // The previous pipelines don't match the new render pass, so there is nothing left to draw with. Hooks
+            // rebuild their objects for the new render pass whether or not that succeeds.
+            self.destroy_assets();
+            let result = self.recreate_render_pass().and_then(|_| self.replace_pipeline());
+            self.upload_assets()?;
+            result?;
+        }
+
+        create_swapchain_image_views(&self.device, &mut self.data)?;
+        create_render_targets(&self.device, &mut self.data)?;
+        create_framebuffers(&self.device, &self.data.swapchain_image_views, *self.data.color_image_view, *self.data.depth_image_view, &self.data.render_pass, &self.data.swapchain_extent, &mut self.data.framebuffers)?;
+        create_uniform_buffers(&mut self.data)?;
+        self.data.render_graph.create_buffers(
+            &self.device,
+            &self.data.allocator,
+            *self.data.command_pool,
+            self.data.graphics_queue,
+            self.data.swapchain_extent,
+        )?;
+        create_pass_descriptor_sets(&self.device, &mut self.data)?;
+
+        self.data
+            .images_in_flight
+            .resize(self.data.swapchain_images.len(), vk::Fence::null());
+        
+        Ok(())
+    }
+
+    /// Drops the swapchain and everything sized or counted by it but the uniform buffers (see
+    /// `create_uniform_buffers`), no frame may be in flight.
+    unsafe fn destroy_swapchain(&mut self) {
+        self.data.descriptor_pool = DescriptorPool::default();
+        self.data.render_graph.destroy_buffers();
+        self.data.framebuffers.clear();
+        destroy_render_targets(&mut self.data);
+        self.data.swapchain_image_views.clear();
+        self.data.swapchain = Swapchain::default();
+    }
+
+    /// Writes the inputs of this frame to the uniform buffer of the image and returns the ones to push.
+    unsafe fn update_inputs(&mut self, image_index: usize) -> Result<PushConstants> {
+        let now = Instant::now();
+        let time_delta = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+
+        let push_constants = PushConstants::new(
+            self.data.swapchain_extent,
+            now.duration_since(self.start).as_secs_f32(),
+            self.frames_rendered as i32,
+        );
+
+        let ubo = UniformBufferObject {
+            time_delta,
+            frame_rate: if time_delta > 0.0 { 1.0 / time_delta } else { 0.0 },
+            mouse_buttons: self.input.mouse.buttons(),
+            _padding0: 0.0,
+            mouse: self.input.mouse.uniform(),
+            date: current_date(),
+            cursor: self.input.mouse.cursor(),
+            _padding1: [0.0; 2],
+            keyboard: self.input.keyboard.uniform(),
+        };
+
+        write_uniform_buffer(self.data.uniform_buffers[image_index].allocation(), &ubo)?;
+
+        self.frames_rendered += 1;
+        self.input.end_frame();
+
+        Ok(push_constants)
+    }
+}
+
+impl Drop for Engine {
+    /// Waits for the device to finish and saves the pipeline cache, the Vulkan objects are destroyed as the
+    /// engine's fields are dropped, each before the device and instance it was created from.
+    fn drop(&mut self) {
+        unsafe {
+            if let Err(error) = self.device.device_wait_idle() {
+                warn!("Failed to wait for the device before destroying it: {}", error);
+            }
+
+            if let Err(error) = save_pipeline_cache(&self.device, *self.data.pipeline_cache, &pipeline_cache_path()) {
+                warn!("Failed to save the pipeline cache: {}", error);
+            }
+
+            // Destroyed before the device was recreated, which failed.
+            if !self.device_lost {
+                self.destroy_assets();
+            }
+            self.destroy_swapchain();
+        }
+    }
+}
+
+/// The Vulkan objects and associated properties used by the engine. The objects it owns are destroyed when
+/// dropped, the surface belongs to the instance.
+#[derive(Debug, Default)]
+struct AppData {
+    /// Whether the validation layer is enabled, see `Config::validation`.
+    validation: bool,
+    surface: vk::SurfaceKHR,
+    physical_device: vk::PhysicalDevice,
+    graphics_queue: vk::Queue,
+    present_queue: vk::Queue,
+    /// The present mode the swapchain is created with if supported, see `Config::present_mode`.
+    present_mode: vk::PresentModeKHR,
+    swapchain: Swapchain,
+    swapchain_format: vk::Format,
+    swapchain_extent: vk::Extent2D,
+    swapchain_images: Vec<vk::Image>,
+    swapchain_image_views: Vec<ImageView>,
+    depth_format: vk::Format,
+    depth_image: Image,
+    depth_image_view: ImageView,
+    /// The samples per pixel of the image pass, `_1` without MSAA.
+    msaa_samples: vk::SampleCountFlags,
+    max_msaa_samples: vk::SampleCountFlags,
+    /// The multisampled image the image pass draws to and resolves into the swapchain image, null without MSAA.
+    color_image: Image,
+    color_image_view: ImageView,
+    render_pass: RenderPass,
+    descriptor_set_layout: DescriptorSetLayout,
+    pipeline_layout: PipelineLayout,
+    pipeline: Pipeline,
+    /// Shared by all pipelines and kept on disk between runs, see `pipeline_cache_path`.
+    pipeline_cache: PipelineCache,
+    framebuffers: Vec<Framebuffer>,
+    allocator: MemoryAllocator,
+    command_pool: CommandPool,
+    transfer: TransferQueue,
+    /// The commands of each frame in flight.
+    frames: Vec<FrameCommands>,
+    image_available_semaphores: Vec<Semaphore>,
+    render_finished_semaphores: Vec<Semaphore>,
+    in_flight_fences: Vec<Fence>,
+    /// The fence of the frame each swapchain image was last drawn by, owned by `in_flight_fences`.
+    images_in_flight: Vec<vk::Fence>,
+    uniform_buffers: Vec<Buffer>,
+    descriptor_pool: DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    /// The fullscreen quad drawn by every pass.
+    quad: Mesh,
+    /// The model drawn by the image pass instead of the quad, if the scene has one.
+    mesh: Option<Mesh>,
+    render_graph: RenderGraph,
+    /// Objects replaced while frames using them may still be in flight, see `Engine::replace_pipeline`.
+    deletion_queue: DeletionQueue,
+}
+
+/// Creates the depth image and, with MSAA, the multisampled color image for the current swapchain extent.
+unsafe fn create_render_targets(device: &SharedDevice, data: &mut AppData) -> Result<()> {
+    (data.depth_image, data.depth_image_view) = create_depth_objects(
+        device,
+        &data.allocator,
+        data.swapchain_extent,
+        data.depth_format,
+        data.msaa_samples,
+    )?;
+
+    if data.msaa_samples != vk::SampleCountFlags::_1 {
+        (data.color_image, data.color_image_view) = create_color_objects(
+            device,
+            &data.allocator,
+            data.swapchain_extent,
+            data.swapchain_format,
+            data.msaa_samples,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Drops the render targets, leaving a null color image view so none is attached until MSAA is used again.
+fn destroy_render_targets(data: &mut AppData) {
+    data.depth_image_view = ImageView::default();
+    data.depth_image = Image::default();
+    data.color_image_view = ImageView::default();
+    data.color_image = Image::default();
+}
+
+/// The requested sample count, or the highest supported one below it.
+fn clamp_msaa_samples(samples: vk::SampleCountFlags, max: vk::SampleCountFlags) -> vk::SampleCountFlags {
+    if samples.bits() > max.bits() {
+        warn!("{:?} MSAA samples aren't supported, using {:?}", samples, max);
+        max
+    } else {
+        samples
+    }
+}
+
+/// Creates the render graph of the scene with its buffers, mesh and the pipelines of all passes.
+unsafe fn create_render_graph(
+    instance: &Instance,
+    device: &SharedDevice,
+    shader_manager: &ShaderManager,
+    data: &mut AppData,
+) -> Result<()> {
+    data.render_graph = RenderGraph::create(
+        instance,
+        device,
+        &data.physical_device,
+        &data.allocator,
+        *data.command_pool,
+        data.graphics_queue,
+        shader_manager.render_graph_desc()?,
+        shader_manager.shaders_dir(),
+    )?;
+
+    data.render_graph.create_buffers(
+        device,
+        &data.allocator,
+        *data.command_pool,
+        data.graphics_queue,
+        data.swapchain_extent,
+    )?;
+
+    if let Some(mesh) = &data.render_graph.desc.mesh {
+        let path = shader_manager.shaders_dir().join(mesh);
+        data.mesh = Some(Mesh::load(device, &data.allocator, &data.transfer, &path)?);
+    }
+
+    let pipelines = data.render_graph.create_pipelines(
+        device,
+        *data.pipeline_cache,
+        shader_manager,
+        &data.descriptor_set_layout,
+        &data.render_pass,
+        data.msaa_samples,
+    )?;
+    (data.pipeline_layout, data.pipeline) = pipelines.image;
+    data.render_graph.pipelines = pipelines.buffers;
+
+    Ok(())
+}
+
+/// Creates the descriptor pool and one descriptor set per swapchain image for every pass of the render graph.
+unsafe fn create_pass_descriptor_sets(device: &SharedDevice, data: &mut AppData) -> Result<()> {
+    let image_count = data.swapchain_images.len();
+    let pass_count = data.render_graph.desc.passes.len();
+    data.descriptor_pool = create_descriptor_pool(device, (image_count * pass_count) as u32)?;
+
+    for index in 0..data.render_graph.buffers.len() {
+        let channels = data.render_graph.channel_images(index);
+        data.render_graph.buffers[index].descriptor_sets = create_descriptor_sets(
+            device,
+            &data.descriptor_set_layout,
+            &data.descriptor_pool,
+            image_count,
+            &data.uniform_buffers,
+            &channels,
+        )?;
+    }
+
+    let channels = data.render_graph.channel_images(data.render_graph.buffers.len());
+    data.descriptor_sets = create_descriptor_sets(
+        device,
+        &data.descriptor_set_layout,
+        &data.descriptor_pool,
+        image_count,
+        &data.uniform_buffers,
+        &channels,
+    )?;
+
+    Ok(())
+}
+
+unsafe fn create_uniform_buffers(data: &mut AppData) -> Result<()> {
+    // They don't depend on the extent, so a resize only recreates them when the number of images changed.
+    if data.uniform_buffers.len() == data.swapchain_images.len() {
+        return Ok(());
+    }
+
+    data.uniform_buffers.clear();
+
+    for _ in 0..data.swapchain_images.len() {
+        let uniform_buffer = create_buffer(
+            &data.allocator,
+            size_of::<UniformBufferObject>() as u64,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        data.uniform_buffers.push(uniform_buffer);
+    }
+
+    Ok(())
+}
+
+/// Copies the uniforms into a uniform buffer, which stays mapped for as long as it exists.
+unsafe fn write_uniform_buffer(allocation: &Allocation, ubo: &UniformBufferObject) -> Result<()> {
+    memcpy(ubo, allocation.mapped_ptr()?.cast(), 1);
+
+    Ok(())
+}
+
+/// Creates the semaphores and fence of each of the `frames_in_flight` frames.
+unsafe fn create_sync_objects(device: &SharedDevice, data: &mut AppData, frames_in_flight: usize) -> Result<()> {
+    let semaphore_info = vk::SemaphoreCreateInfo::builder();
+    let fence_info = vk::FenceCreateInfo::builder()
+        .flags(vk::FenceCreateFlags::SIGNALED);
+
+    for _ in 0..frames_in_flight {
+        data.image_available_semaphores.push(Owned::new(device, device.create_semaphore(&semaphore_info, None)?));
+        data.render_finished_semaphores.push(Owned::new(device, device.create_semaphore(&semaphore_info, None)?));
+
+        data.in_flight_fences.push(Owned::new(device, device.create_fence(&fence_info, None)?));
+    }
+
+    data.images_in_flight = data.swapchain_images
+        .iter()
+        .map(|_| vk::Fence::null())
+        .collect();
+
+    Ok(())
+}
+
+/// Creates the command pool and buffer of `count` frames in flight, they're recorded by `record_command_buffer`
+/// every time the frame is rendered.
+unsafe fn create_frame_commands(instance: &Instance, device: &SharedDevice, data: &mut AppData, count: usize) -> Result<()> {
+    let indices = QueueFamilyIndices::get(instance, &data.surface, data.physical_device)?;
+
+    for _ in 0..count {
+        data.frames.push(FrameCommands::create(device, indices.graphics)?);
+    }
+
+    Ok(())
+}
+
+/// Records the passes of the render graph and the image pass drawing to a swapchain image into a begun command
+/// buffer, and ends it.
+unsafe fn record_command_buffer(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    image_index: usize,
+    push_constants: &PushConstants,
+    draw_hooks: &mut [Box<dyn DrawHook>],
+) -> Result<()> {
+    data.render_graph.record(
+        device,
+        command_buffer,
+        image_index,
+        data.swapchain_extent,
+        &data.quad,
+        push_constants,
+    );
+
+    let render_area = vk::Rect2D::builder()
+        .offset(vk::Offset2D::default())
+        .extent(data.swapchain_extent);
+    
+    let color_clear_value = vk::ClearValue {
+        color: vk::ClearColorValue {
+            float32: [0.0, 0.0, 0.0, 1.0],
+        },
+    };
+        
+    let depth_clear_value = vk::ClearValue {
+        depth_stencil: vk::ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        },
+    };
+
+    let clear_values = &[color_clear_value, depth_clear_value];
+    let info = vk::RenderPassBeginInfo::builder()
+        .render_pass(*data.render_pass)
+        .framebuffer(*data.framebuffers[image_index])
+        .render_area(render_area)
+        .clear_values(clear_values);
+
+    device.cmd_begin_render_pass(
+        command_buffer, &info, vk::SubpassContents::INLINE);    
+
+    device.cmd_bind_pipeline(
+        command_buffer, vk::PipelineBindPoint::GRAPHICS, *data.pipeline);
+
+    record_viewport(device, command_buffer, data.swapchain_extent);
+
+    device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        *data.pipeline_layout,
+        0,
+        &[data.descriptor_sets[image_index]],
+        &[],
+    );
+
+    push_constants.record(device, command_buffer, *data.pipeline_layout);
+
+    data.mesh.as_ref().unwrap_or(&data.quad).record_draw(device, command_buffer);
+
+    let context = DrawContext {
+        command_buffer,
+        image_index,
+        extent: data.swapchain_extent,
+        pipeline_layout: *data.pipeline_layout,
+        push_constants,
+    };
+    draw_hooks.iter_mut().for_each(|hook| hook.record(device, &context));
+        
+    device.cmd_end_render_pass(command_buffer);
+
+    device.end_command_buffer(command_buffer)?;
+
+    Ok(())
+}
+
+/// Creates the graphics command pool for one time commands and the pool of the transfer queue used for uploads.
+unsafe fn create_command_pool(
+    instance: &Instance,
+    device: &SharedDevice,
+    data: &mut AppData,
+) -> Result<()> {
+    let indices = QueueFamilyIndices::get(instance, &data.surface, data.physical_device)?;
+
+    let info = vk::CommandPoolCreateInfo::builder()
+        .flags(vk::CommandPoolCreateFlags::empty()) // Optional.
+        .queue_family_index(indices.graphics);
+
+    data.command_pool = Owned::new(device, device.create_command_pool(&info, None)?);
+
+    let transfer = indices.transfer.unwrap_or(indices.graphics);
+    data.transfer = TransferQueue::create(device, transfer, indices.graphics)?;
+
+    Ok(())
+}
+
+unsafe fn create_logical_device(
+    instance: &SharedInstance,
+    data: &mut AppData,
+) -> Result<SharedDevice> {
+    let indices = QueueFamilyIndices::get(instance, &data.surface, data.physical_device)?;
+
+    // HashSet cannot contain duplicates, thus if the indices are equal, there will only be built one queue_family.
+    let mut unique_indices = HashSet::new();
+    unique_indices.insert(indices.graphics);
+    unique_indices.insert(indices.present);
+    if let Some(transfer) = indices.transfer {
+        unique_indices.insert(transfer);
+    }
+
+    let queue_priorities = &[1.0];
+    let queue_infos = unique_indices
+        .iter()
+        .map(|i| {
+            vk::DeviceQueueCreateInfo::builder()
+                .queue_family_index(*i)
+                .queue_priorities(queue_priorities)
+        })
+        .collect::<Vec<_>>();
+
+    let layers = if data.validation {
+        vec![VALIDATION_LAYER.as_ptr()]
+    } else {
+        vec![]
+    };
+    
+    // Scenes drawing lines or points need it, see `RenderGraph::create`.
+    let supported = instance.get_physical_device_features(data.physical_device);
+    let features = vk::PhysicalDeviceFeatures::builder()
+        .fill_mode_non_solid(supported.fill_mode_non_solid == vk::TRUE);
+
+    let extensions = required_device_extensions(data)
+        .iter()
+        .map(|n| n.as_ptr())
+        .collect::<Vec<_>>();
+
+    let info = vk::DeviceCreateInfo::builder()
+        .queue_create_infos(&queue_infos)
+        .enabled_layer_names(&layers)
+        .enabled_extension_names(&extensions)
+        .enabled_features(&features);
+
+    let device = Rc::new(OwnedDevice::new(instance, instance.create_device(data.physical_device, &info, None)?));
+
+    data.graphics_queue = device.get_device_queue(indices.graphics, 0);
+    data.present_queue = device.get_device_queue(indices.present, 0);
+
+    Ok(device)
+}
+
+
+
+unsafe fn pick_physical_device(instance: &Instance, data: &mut AppData) -> Result<()> {
+    for physical_device in instance.enumerate_physical_devices()? {
+        let properties = instance.get_physical_device_properties(physical_device);
+
+        if let Err(error) = check_physical_device(instance, data, physical_device) {
+            warn!("Skipping physical device (`{}`): {}", properties.device_name, error);
+        } else {
+            info!("Selected physical device (`{}`).", properties.device_name);
+            data.physical_device = physical_device;
+            return Ok(());
+        }
+    }
+
+    Err(EngineError::NoSuitableDevice.into())
+}
+
+unsafe fn check_physical_device(
+    instance: &Instance,
+    data: &AppData,
+    physical_device: vk::PhysicalDevice,
+) -> Result<()> {
+
+    let properties = instance
+        .get_physical_device_properties(physical_device);
+
+    // if properties.device_type != vk::PhysicalDeviceType::DISCRETE_GPU {
+    //     return Err(anyhow!(SuitabilityError("Only discrete GPUs are supported.")));
+    // }
+    
+    let version = properties.api_version;
+    if vk::version_major(version) < 1 || vk::version_minor(version) < 1 {
+        let (major, minor) = (vk::version_major(version), vk::version_minor(version));
+        return Err(SuitabilityError::ApiVersion { major, minor }.into());
+    }
+
+    let features = instance
+        .get_physical_device_features(physical_device);
+
+    if features.geometry_shader != vk::TRUE {
+        return Err(SuitabilityError::GeometryShader.into());
+    }
+
+    QueueFamilyIndices::get(instance, &data.surface, physical_device)?;
+
+    check_physical_device_extensions(instance, data, physical_device)?;
+
+    // Headless rendering has no surface to present to.
+    if data.surface.is_null() {
+        return Ok(());
+    }
+
+    let support = SwapchainSupport::get(instance, &data.surface, physical_device)?;
+    if support.formats.is_empty() || support.present_modes.is_empty() {
+        return Err(SuitabilityError::Swapchain.into());
+    }
+    
+    Ok(())    
+}
+
+unsafe fn check_physical_device_extensions(
+    instance: &Instance,
+    data: &AppData,
+    physical_device: vk::PhysicalDevice,
+) -> Result<()> {
+    let extensions = instance
+        .enumerate_device_extension_properties(physical_device, None)?
+        .iter()
+        .map(|e| e.extension_name)
+        .collect::<HashSet<_>>();
+
+    let missing = required_device_extensions(data)
+        .iter()
+        .filter(|e| !extensions.contains(e))
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(SuitabilityError::Extensions(missing).into())
+    }
+}
+
+/// The device extensions needed, the swapchain extension is only required when rendering to a surface.
+fn required_device_extensions(data: &AppData) -> &'static [vk::ExtensionName] {
+    if data.surface.is_null() {
+        &[]
+    } else {
+        DEVICE_EXTENSIONS
+    }
+}
+
+unsafe fn create_swapchain_image_views(
+    device: &SharedDevice,
+    data: &mut AppData,
+) -> Result<()> {
+    data.swapchain_image_views = data
+        .swapchain_images
+        .iter()
+        .map(|i| create_image_view(device, *i, data.swapchain_format, vk::ImageAspectFlags::COLOR, 1))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(())
+}
+
+extern "system" fn debug_callback(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    type_: vk::DebugUtilsMessageTypeFlagsEXT,
+    data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _: *mut c_void,
+) -> vk::Bool32 {
+    let data = unsafe { *data };
+    let message = unsafe { CStr::from_ptr(data.message) }.to_string_lossy();
+
+    if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::ERROR {
+        error!("({:?}) {}", type_, message);
+    } else if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::WARNING {
+        warn!("({:?}) {}", type_, message);
+    } else if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::INFO {
+        debug!("({:?}) {}", type_, message);
+    } else {
+        trace!("({:?}) {}", type_, message);
+    }
+
+    vk::FALSE
+}
\ No newline at end of file
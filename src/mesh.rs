@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use nalgebra_glm as glm;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::allocator::*;
+use crate::buffers::create_index_buffer::*;
+use crate::buffers::create_vertex_buffer::*;
+use crate::buffers::upload::TransferQueue;
+use crate::vertex::*;
+
+/// Indexed triangles in device local vertex and index buffers, drawn with `cmd_draw_indexed`.
+#[derive(Debug, Default)]
+pub struct Mesh {
+    pub vertex_buffer: Buffer,
+    pub index_buffer: Buffer,
+    pub index_count: u32,
+}
+
+impl Mesh {
+    pub unsafe fn create(
+        device: &Device,
+        allocator: &MemoryAllocator,
+        transfer: &TransferQueue,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Result<Self> {
+        let vertex_buffer = create_vertex_buffer(device, allocator, transfer, vertices)?;
+        let index_buffer = create_index_buffer(device, allocator, transfer, indices)?;
+
+        Ok(Self { vertex_buffer, index_buffer, index_count: indices.len() as u32 })
+    }
+
+    /// The fullscreen quad from `VERTICES` and `INDICES`.
+    pub unsafe fn quad(
+        device: &Device,
+        allocator: &MemoryAllocator,
+        transfer: &TransferQueue,
+    ) -> Result<Self> {
+        Self::create(device, allocator, transfer, &VERTICES, INDICES)
+    }
+
+    /// Loads all models of an OBJ file into one mesh.
+    pub unsafe fn load(
+        device: &Device,
+        allocator: &MemoryAllocator,
+        transfer: &TransferQueue,
+        path: &Path,
+    ) -> Result<Self> {
+        let (vertices, indices) = load_obj(path)?;
+
+        debug!("Loaded {:?} with {} vertices and {} triangles", path, vertices.len(), indices.len() / 3);
+
+        Self::create(device, allocator, transfer, &vertices, &indices)
+    }
+
+    pub unsafe fn record_draw(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[*self.vertex_buffer], &[0]);
+        device.cmd_bind_index_buffer(command_buffer, *self.index_buffer, 0, vk::IndexType::UINT32);
+        device.cmd_draw_indexed(command_buffer, self.index_count, 1, 0, 0, 0);
+    }
+}
+
+/// Reads the triangles of all models in an OBJ file. Missing normals and texture coordinates are zero.
+pub fn load_obj(path: &Path) -> Result<(Vec<Vertex>, Vec<u32>)> {
+    let options = tobj::LoadOptions { single_index: true, triangulate: true, ..Default::default() };
+    let (models, _) = tobj::load_obj(path, &options).map_err(|e| anyhow!("Failed to load {:?}: {}", path, e))?;
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for model in &models {
+        let mesh = &model.mesh;
+        let offset = vertices.len() as u32;
+
+        for i in 0..mesh.positions.len() / 3 {
+            let pos = glm::vec3(mesh.positions[3 * i], mesh.positions[3 * i + 1], mesh.positions[3 * i + 2]);
+            let normal = match mesh.normals.get(3 * i..3 * i + 3) {
+                Some(n) => glm::vec3(n[0], n[1], n[2]),
+                None => glm::Vec3::zeros(),
+            };
+            let uv = match mesh.texcoords.get(2 * i..2 * i + 2) {
+                Some(t) => glm::vec2(t[0], t[1]),
+                None => glm::Vec2::zeros(),
+            };
+
+            vertices.push(Vertex::new(pos, normal, uv));
+        }
+
+        indices.extend(mesh.indices.iter().map(|i| offset + i));
+    }
+
+    if indices.is_empty() {
+        return Err(anyhow!("{:?} contains no triangles.", path));
+    }
+
+    Ok((vertices, indices))
+}
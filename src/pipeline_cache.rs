@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::resources::*;
+
+/// The size of the header Vulkan puts in front of the pipeline cache data (`VkPipelineCacheHeaderVersionOne`).
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+/// Where the pipeline cache is kept between runs, `PIPELINE_CACHE` or a file in the temp dir.
+pub fn pipeline_cache_path() -> PathBuf {
+    match std::env::var("PIPELINE_CACHE") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => std::env::temp_dir().join("rust_vulkan_engine.pipeline_cache"),
+    }
+}
+
+/// Creates the pipeline cache all pipelines are built with, seeded from the file at `path` if it was written for
+/// the same physical device and driver. A missing or stale file starts an empty cache.
+pub unsafe fn create_pipeline_cache(
+    instance: &Instance,
+    device: &SharedDevice,
+    physical_device: vk::PhysicalDevice,
+    path: &Path,
+) -> Result<PipelineCache> {
+    let properties = instance.get_physical_device_properties(physical_device);
+
+    let data = match fs::read(path) {
+        Ok(data) if is_compatible(&data, &properties) => {
+            info!("Loaded {} bytes of pipeline cache from {:?}", data.len(), path);
+            data
+        }
+        Ok(_) => {
+            info!("Ignoring the pipeline cache in {:?}, it was written for another device or driver", path);
+            Vec::new()
+        }
+        Err(error) => {
+            debug!("No pipeline cache in {:?}: {}", path, error);
+            Vec::new()
+        }
+    };
+
+    let info = vk::PipelineCacheCreateInfo::builder()
+        .initial_data(&data);
+
+    Ok(Owned::new(device, device.create_pipeline_cache(&info, None)?))
+}
+
+/// Writes the contents of the pipeline cache to `path`, through a temporary file so a crash never leaves a
+/// truncated cache behind.
+pub unsafe fn save_pipeline_cache(device: &Device, pipeline_cache: vk::PipelineCache, path: &Path) -> Result<()> {
+    let data = device.get_pipeline_cache_data(pipeline_cache)?;
+
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, &data)?;
+    fs::rename(&temporary, path)?;
+
+    debug!("Saved {} bytes of pipeline cache to {:?}", data.len(), path);
+
+    Ok(())
+}
+
+/// Checks the cache header against the vendor, device and cache UUID of the physical device.
+fn is_compatible(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+
+    // The header is little endian whatever the host byte order is.
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+    read_u32(0) as usize >= HEADER_SIZE
+        && read_u32(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && read_u32(8) == properties.vendor_id
+        && read_u32(12) == properties.device_id
+        && data[16..HEADER_SIZE] == properties.pipeline_cache_uuid[..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties() -> vk::PhysicalDeviceProperties {
+        vk::PhysicalDeviceProperties {
+            vendor_id: 0x10de,
+            device_id: 0x2204,
+            pipeline_cache_uuid: vk::ByteArray([7; vk::UUID_SIZE]),
+            ..Default::default()
+        }
+    }
+
+    fn header(properties: &vk::PhysicalDeviceProperties) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        data.extend_from_slice(&(vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32).to_le_bytes());
+        data.extend_from_slice(&properties.vendor_id.to_le_bytes());
+        data.extend_from_slice(&properties.device_id.to_le_bytes());
+        data.extend_from_slice(&properties.pipeline_cache_uuid[..]);
+        data.extend_from_slice(&[1, 2, 3]);
+        data
+    }
+
+    #[test]
+    fn matching_header_is_compatible() {
+        assert!(is_compatible(&header(&properties()), &properties()));
+    }
+
+    #[test]
+    fn short_data_is_incompatible() {
+        let data = header(&properties());
+        assert!(!is_compatible(&data[..HEADER_SIZE - 1], &properties()));
+        assert!(!is_compatible(&[], &properties()));
+    }
+
+    #[test]
+    fn other_devices_are_incompatible() {
+        let other_vendor = vk::PhysicalDeviceProperties { vendor_id: 0x1002, ..properties() };
+        assert!(!is_compatible(&header(&other_vendor), &properties()));
+
+        let other_device = vk::PhysicalDeviceProperties { device_id: 0x2206, ..properties() };
+        assert!(!is_compatible(&header(&other_device), &properties()));
+
+        let other_uuid =
+            vk::PhysicalDeviceProperties { pipeline_cache_uuid: vk::ByteArray([8; vk::UUID_SIZE]), ..properties() };
+        assert!(!is_compatible(&header(&other_uuid), &properties()));
+    }
+}
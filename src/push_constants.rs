@@ -0,0 +1,48 @@
+use std::mem::size_of;
+use std::slice;
+
+use nalgebra_glm as glm;
+use vulkanalia::prelude::v1_0::*;
+
+/// The stages reading the push constants, vertex shaders need the resolution and time for their cameras.
+pub const PUSH_CONSTANT_STAGES: vk::ShaderStageFlags =
+    vk::ShaderStageFlags::from_bits_truncate(vk::ShaderStageFlags::VERTEX.bits() | vk::ShaderStageFlags::FRAGMENT.bits());
+
+/// The Shadertoy inputs that change every frame, pushed with `cmd_push_constants` instead of written to the
+/// uniform buffer. Laid out to match the `ShadertoyFrame` push constant block declared by the shader prelude.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PushConstants {
+    /// `iResolution`, the viewport size in pixels, z is the pixel aspect ratio.
+    pub resolution: glm::Vec3,
+    /// `iTime`, seconds since startup.
+    pub time: f32,
+    /// `iFrame`, the number of frames rendered before this one.
+    pub frame: i32,
+}
+
+impl PushConstants {
+    pub fn new(extent: vk::Extent2D, time: f32, frame: i32) -> Self {
+        Self {
+            resolution: glm::vec3(extent.width as f32, extent.height as f32, 1.0),
+            time,
+            frame,
+        }
+    }
+
+    /// The push constant range of every pipeline layout.
+    pub fn range() -> vk::PushConstantRange {
+        vk::PushConstantRange::builder()
+            .stage_flags(PUSH_CONSTANT_STAGES)
+            .offset(0)
+            .size(size_of::<Self>() as u32)
+            .build()
+    }
+
+    /// Records the push of these constants for the pipelines created with `range`.
+    pub unsafe fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, pipeline_layout: vk::PipelineLayout) {
+        let bytes = slice::from_raw_parts((self as *const Self).cast::<u8>(), size_of::<Self>());
+
+        device.cmd_push_constants(command_buffer, pipeline_layout, PUSH_CONSTANT_STAGES, 0, bytes);
+    }
+}
@@ -1,17 +1,16 @@
 use vulkanalia::prelude::v1_0::*;
 use vulkanalia::vk::KhrSurfaceExtension;
 
-use anyhow::{anyhow, Result};
-use thiserror::Error;
+use anyhow::Result;
 
-#[derive(Debug, Error)]
-#[error("Missing {0}.")]
-pub struct SuitabilityError(pub &'static str);
+use crate::error::SuitabilityError;
 
 #[derive(Copy, Clone, Debug)]
 pub struct QueueFamilyIndices {
     pub graphics: u32,
     pub present: u32,
+    /// A family dedicated to transfers (without graphics), usually backed by a separate DMA engine.
+    pub transfer: Option<u32>,
 }
 
 impl QueueFamilyIndices {
@@ -29,21 +28,34 @@ impl QueueFamilyIndices {
             .map(|i| i as u32);
 
         let mut present = None;
-        for (index, properties) in properties.iter().enumerate() {
-            if instance.get_physical_device_surface_support_khr(
-                physical_device,
-                index as u32,
-                *surface,
-            )? {
-                present = Some(index as u32);
-                break;
+        if surface.is_null() {
+            // Without a surface (headless rendering) nothing is presented, so the graphics family stands in.
+            present = graphics;
+        } else {
+            for (index, properties) in properties.iter().enumerate() {
+                if instance.get_physical_device_surface_support_khr(
+                    physical_device,
+                    index as u32,
+                    *surface,
+                )? {
+                    present = Some(index as u32);
+                    break;
+                }
             }
         }
 
+        let transfer = properties
+            .iter()
+            .position(|p| {
+                p.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                    && !p.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+            })
+            .map(|i| i as u32);
+
         if let (Some(graphics), Some(present)) = (graphics, present) {
-            Ok(Self { graphics, present })
+            Ok(Self { graphics, present, transfer })
         } else {
-            Err(anyhow!(SuitabilityError("Missing required queue families.")))
+            Err(SuitabilityError::QueueFamilies.into())
         }
     }
 }
\ No newline at end of file
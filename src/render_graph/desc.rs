@@ -0,0 +1,318 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use vulkanalia::prelude::v1_0::*;
+
+use super::CHANNEL_COUNT;
+use crate::create_pipeline::{BlendMode, DepthOptions};
+
+/// The name of the pass drawn to the screen, always the last one.
+pub const IMAGE_PASS: &str = "image";
+
+/// A fullscreen pass running a fragment shader.
+#[derive(Clone, Debug, Default)]
+pub struct PassDesc {
+    pub name: String,
+    pub fragment_shader: String,
+    /// The passes or textures bound to `iChannel0` to `iChannel3`, `None` for unbound channels.
+    pub channels: Vec<Option<String>>,
+}
+
+/// The passes making up a scene, in the order they run each frame.
+///
+/// A channel bound to a pass that ran earlier in the frame samples its output from this frame, a channel
+/// bound to the pass itself or a later one samples its output from the previous frame.
+#[derive(Clone, Debug, Default)]
+pub struct RenderGraphDesc {
+    pub passes: Vec<PassDesc>,
+    /// An OBJ file in the shaders dir the image pass draws with `mesh.vert`, instead of the fullscreen quad.
+    pub mesh: Option<String>,
+    /// The depth test and writes of the image pass.
+    pub depth: DepthOptions,
+    /// How the image pass blends into the cleared screen.
+    pub blend: BlendMode,
+    /// The faces the image pass culls.
+    pub cull_mode: vk::CullModeFlags,
+    /// Whether the image pass fills its triangles or only draws their edges or vertices.
+    pub polygon_mode: vk::PolygonMode,
+}
+
+impl RenderGraphDesc {
+    /// A scene made of only the image pass, without channels.
+    pub fn single(fragment_shader: &str) -> Self {
+        Self {
+            passes: vec![PassDesc {
+                name: IMAGE_PASS.to_string(),
+                fragment_shader: fragment_shader.to_string(),
+                channels: Vec::new(),
+            }],
+            mesh: None,
+            depth: DepthOptions::default(),
+            blend: BlendMode::default(),
+            cull_mode: vk::CullModeFlags::BACK,
+            polygon_mode: vk::PolygonMode::FILL,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read scene {:?}: {}", path, e))?;
+
+        Self::parse(&source).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    /// Parses a scene with one pass per line, `<pass> = <fragment shader> [<channel>...]`.
+    ///
+    /// Channels name the pass or the PNG texture (a file in the shaders dir) bound to `iChannel0` onwards, `-`
+    /// leaves a channel unbound. Empty lines and
+    /// lines starting with `#` are ignored. The last pass must be the `image` pass.
+    ///
+    /// A `mesh <file.obj>` line draws the image pass as that model instead of a fullscreen quad. A
+    /// `depth on|test|off` line sets whether the image pass tests and writes depth (on), only tests it or
+    /// ignores the depth buffer, it defaults to on.
+    ///
+    /// A `blend off|alpha|additive|premultiplied` line sets how the image pass blends, it defaults to off. A
+    /// `cull back|front|none` line sets the faces the image pass culls, it defaults to back. A
+    /// `polygon fill|line|point` line sets whether it fills triangles or draws their edges or vertices, which needs
+    /// the `fillModeNonSolid` device feature, it defaults to fill.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut passes = Vec::new();
+        let mut mesh = None;
+        let mut depth = DepthOptions::default();
+        let mut blend = BlendMode::default();
+        let mut cull_mode = vk::CullModeFlags::BACK;
+        let mut polygon_mode = vk::PolygonMode::FILL;
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: &str| anyhow!("line {}: {}", index + 1, message);
+
+            if let Some(file) = line.strip_prefix("mesh ") {
+                mesh = Some(file.trim().to_string());
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix("depth ") {
+                depth = match value.trim() {
+                    "on" => DepthOptions::ENABLED,
+                    "test" => DepthOptions { test: true, write: false },
+                    "off" => DepthOptions::DISABLED,
+                    _ => return Err(error("expected `depth on`, `depth test` or `depth off`")),
+                };
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix("blend ") {
+                blend = match value.trim() {
+                    "off" => BlendMode::Opaque,
+                    "alpha" => BlendMode::Alpha,
+                    "additive" => BlendMode::Additive,
+                    "premultiplied" => BlendMode::Premultiplied,
+                    _ => return Err(error("expected `blend off`, `blend alpha`, `blend additive` or `blend premultiplied`")),
+                };
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix("cull ") {
+                cull_mode = match value.trim() {
+                    "back" => vk::CullModeFlags::BACK,
+                    "front" => vk::CullModeFlags::FRONT,
+                    "none" => vk::CullModeFlags::NONE,
+                    _ => return Err(error("expected `cull back`, `cull front` or `cull none`")),
+                };
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix("polygon ") {
+                polygon_mode = match value.trim() {
+                    "fill" => vk::PolygonMode::FILL,
+                    "line" => vk::PolygonMode::LINE,
+                    "point" => vk::PolygonMode::POINT,
+                    _ => return Err(error("expected `polygon fill`, `polygon line` or `polygon point`")),
+                };
+                continue;
+            }
+
+            let (name, rest) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `<pass> = <fragment shader> [<channel>...]`"))?;
+
+            let mut words = rest.split_whitespace();
+            let fragment_shader = words.next().ok_or_else(|| error("missing fragment shader"))?;
+            let channels = words
+                .map(|w| if w == "-" { None } else { Some(w.to_string()) })
+                .collect::<Vec<_>>();
+
+            if channels.len() > CHANNEL_COUNT {
+                return Err(error(&format!("at most {} channels can be bound", CHANNEL_COUNT)));
+            }
+
+            passes.push(PassDesc {
+                name: name.trim().to_string(),
+                fragment_shader: fragment_shader.to_string(),
+                channels,
+            });
+        }
+
+        let desc = Self { passes, mesh, depth, blend, cull_mode, polygon_mode };
+        desc.validate()?;
+
+        Ok(desc)
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self.passes.last() {
+            Some(pass) if pass.name == IMAGE_PASS => {}
+            _ => return Err(anyhow!("the last pass must be the `{}` pass", IMAGE_PASS)),
+        }
+
+        let mut names = HashSet::new();
+        for pass in &self.passes {
+            if !names.insert(pass.name.as_str()) {
+                return Err(anyhow!("pass `{}` is declared twice", pass.name));
+            }
+        }
+
+        for pass in &self.passes {
+            for channel in pass.channels.iter().flatten().filter(|c| !is_texture(c)) {
+                if channel == IMAGE_PASS || !names.contains(channel.as_str()) {
+                    return Err(anyhow!("pass `{}` samples unknown buffer pass `{}`", pass.name, channel));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The offscreen passes, every pass but the image pass.
+    pub fn buffers(&self) -> &[PassDesc] {
+        &self.passes[..self.passes.len() - 1]
+    }
+
+    pub fn image(&self) -> &PassDesc {
+        &self.passes[self.passes.len() - 1]
+    }
+
+    /// The textures bound to any channel, each once.
+    pub fn textures(&self) -> Vec<&str> {
+        let mut textures = Vec::new();
+        for channel in self.passes.iter().flat_map(|p| p.channels.iter().flatten()) {
+            if is_texture(channel) && !textures.contains(&channel.as_str()) {
+                textures.push(channel.as_str());
+            }
+        }
+
+        textures
+    }
+
+    /// The index of the buffer pass with the given name.
+    pub fn buffer_index(&self, name: &str) -> Option<usize> {
+        self.buffers().iter().position(|p| p.name == name)
+    }
+}
+
+/// Channels naming a PNG file bind a texture instead of a pass.
+pub fn is_texture(channel: &str) -> bool {
+    channel.ends_with(".png")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_passes_and_their_channels() {
+        let desc = RenderGraphDesc::parse(
+            "# A comment\n\
+             \n\
+             bufferA = trail_buffer.frag bufferA noise.png\n\
+             image = trail_image.frag - bufferA\n",
+        )
+        .unwrap();
+
+        assert_eq!(desc.passes.len(), 2);
+        assert_eq!(desc.buffers()[0].name, "bufferA");
+        assert_eq!(desc.buffers()[0].fragment_shader, "trail_buffer.frag");
+        assert_eq!(desc.buffers()[0].channels, vec![Some("bufferA".to_string()), Some("noise.png".to_string())]);
+        assert_eq!(desc.image().channels, vec![None, Some("bufferA".to_string())]);
+        assert_eq!(desc.buffer_index("bufferA"), Some(0));
+        assert_eq!(desc.textures(), vec!["noise.png"]);
+    }
+
+    #[test]
+    fn parse_defaults_the_image_pass_state() {
+        let desc = RenderGraphDesc::parse("image = shader.frag").unwrap();
+
+        assert_eq!(desc.mesh, None);
+        assert_eq!(desc.depth, DepthOptions::ENABLED);
+        assert_eq!(desc.blend, BlendMode::Opaque);
+        assert_eq!(desc.cull_mode, vk::CullModeFlags::BACK);
+        assert_eq!(desc.polygon_mode, vk::PolygonMode::FILL);
+    }
+
+    #[test]
+    fn parse_reads_the_image_pass_state() {
+        let desc = RenderGraphDesc::parse(
+            "mesh cube.obj\n\
+             depth test\n\
+             blend premultiplied\n\
+             cull none\n\
+             polygon line\n\
+             image = mesh.frag\n",
+        )
+        .unwrap();
+
+        assert_eq!(desc.mesh.as_deref(), Some("cube.obj"));
+        assert_eq!(desc.depth, DepthOptions { test: true, write: false });
+        assert_eq!(desc.blend, BlendMode::Premultiplied);
+        assert_eq!(desc.cull_mode, vk::CullModeFlags::NONE);
+        assert_eq!(desc.polygon_mode, vk::PolygonMode::LINE);
+
+        let desc = RenderGraphDesc::parse("depth off\nblend additive\npolygon point\nimage = a.frag").unwrap();
+        assert_eq!(desc.depth, DepthOptions::DISABLED);
+        assert_eq!(desc.blend, BlendMode::Additive);
+        assert_eq!(desc.polygon_mode, vk::PolygonMode::POINT);
+    }
+
+    #[test]
+    fn parse_rejects_bad_state_values() {
+        for (source, message) in [
+            ("depth maybe\nimage = a.frag", "line 1: expected `depth on`, `depth test` or `depth off`"),
+            ("blend multiply\nimage = a.frag", "line 1: expected `blend off`, `blend alpha`, `blend additive` or `blend premultiplied`"),
+            ("cull both\nimage = a.frag", "line 1: expected `cull back`, `cull front` or `cull none`"),
+            ("polygon wire\nimage = a.frag", "line 1: expected `polygon fill`, `polygon line` or `polygon point`"),
+        ] {
+            assert_eq!(RenderGraphDesc::parse(source).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_malformed_passes() {
+        for (source, message) in [
+            ("image shader.frag", "line 1: expected `<pass> = <fragment shader> [<channel>...]`"),
+            ("image =", "line 1: missing fragment shader"),
+            ("image = a.frag a b c d e", "line 1: at most 4 channels can be bound"),
+        ] {
+            assert_eq!(RenderGraphDesc::parse(source).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_graphs() {
+        for (source, message) in [
+            ("", "the last pass must be the `image` pass"),
+            ("image = a.frag\nbufferA = b.frag", "the last pass must be the `image` pass"),
+            ("bufferA = a.frag\nbufferA = b.frag\nimage = c.frag", "pass `bufferA` is declared twice"),
+            ("image = a.frag bufferB", "pass `image` samples unknown buffer pass `bufferB`"),
+            ("image = a.frag image", "pass `image` samples unknown buffer pass `image`"),
+        ] {
+            assert_eq!(RenderGraphDesc::parse(source).unwrap_err().to_string(), message);
+        }
+    }
+}
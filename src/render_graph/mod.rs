@@ -0,0 +1,496 @@
+use std::path::Path;
+
+use vulkanalia::prelude::v1_0::*;
+use anyhow::{anyhow, Result};
+use log::*;
+
+use crate::allocator::*;
+use crate::create_image::*;
+use crate::create_pipeline::*;
+use crate::create_renderpass::*;
+use crate::mesh::*;
+use crate::push_constants::*;
+use crate::resources::*;
+use crate::shader_manager::*;
+use crate::single_time_commands::*;
+use crate::texture::*;
+
+mod desc;
+pub use desc::*;
+
+/// The number of `iChannel` inputs every pass has.
+pub const CHANNEL_COUNT: usize = 4;
+
+/// Float buffers so simulations can store values outside of 0..1, like Shadertoy's buffers.
+pub const BUFFER_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// An image with its view.
+#[derive(Debug, Default)]
+pub struct GraphImage {
+    pub view: ImageView,
+    pub image: Image,
+}
+
+impl GraphImage {
+    unsafe fn create(
+        device: &SharedDevice,
+        allocator: &MemoryAllocator,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<Self> {
+        let image = create_image(
+            allocator,
+            extent,
+            1,
+            vk::SampleCountFlags::_1,
+            BUFFER_FORMAT,
+            usage | vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        let view = create_image_view(device, *image, BUFFER_FORMAT, vk::ImageAspectFlags::COLOR, 1)?;
+
+        Ok(Self { view, image })
+    }
+}
+
+/// The swapchain sized resources of an offscreen pass.
+#[derive(Debug, Default)]
+pub struct BufferPass {
+    /// Drawn by the pass every frame.
+    pub target: GraphImage,
+    /// The target's contents from the previous frame, for passes sampling themselves or later passes.
+    pub history: GraphImage,
+    pub framebuffer: Framebuffer,
+    /// One per swapchain image, like the uniform buffers they point to.
+    pub descriptor_sets: Vec<vk::DescriptorSet>,
+}
+
+/// The pipeline layout and pipeline of every pass of a graph, see `RenderGraph::create_pipelines`.
+#[derive(Debug, Default)]
+pub struct GraphPipelines {
+    pub image: (PipelineLayout, Pipeline),
+    pub buffers: Vec<(PipelineLayout, Pipeline)>,
+}
+
+/// Runs the buffer passes of a scene before the image pass draws to the screen.
+///
+/// The buffers have the size of the swapchain and are recreated with it, starting out black.
+#[derive(Debug, Default)]
+pub struct RenderGraph {
+    pub desc: RenderGraphDesc,
+    pub render_pass: RenderPass,
+    /// The sampler of channels bound to buffer passes, clamping to the edge.
+    pub sampler: Sampler,
+    /// A black 1x1 image bound to unused channels.
+    pub placeholder: GraphImage,
+    /// The textures bound to channels by file name, these outlive the buffers.
+    pub textures: Vec<(String, Texture)>,
+    pub buffers: Vec<BufferPass>,
+    /// The pipeline layout and pipeline of each buffer pass, these outlive the buffers.
+    pub pipelines: Vec<(PipelineLayout, Pipeline)>,
+}
+
+impl RenderGraph {
+    /// Creates the parts of the graph that don't depend on the swapchain, see `create_buffers`. Textures are
+    /// loaded from `textures_dir`.
+    pub unsafe fn create(
+        instance: &Instance,
+        device: &SharedDevice,
+        physical_device: &vk::PhysicalDevice,
+        allocator: &MemoryAllocator,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        desc: RenderGraphDesc,
+        textures_dir: &Path,
+    ) -> Result<Self> {
+        debug!("Creating render graph with passes {:?}", desc.passes.iter().map(|p| &p.name).collect::<Vec<_>>());
+
+        // The device enables the feature whenever it's supported.
+        let features = instance.get_physical_device_features(*physical_device);
+        if desc.polygon_mode != vk::PolygonMode::FILL && features.fill_mode_non_solid != vk::TRUE {
+            return Err(anyhow!("The device can't draw {:?} polygons, it lacks `fillModeNonSolid`.", desc.polygon_mode));
+        }
+
+        let render_pass = create_offscreen_render_pass(device, BUFFER_FORMAT)?;
+        let sampler = create_channel_sampler(device)?;
+
+        let placeholder = GraphImage::create(
+            device,
+            allocator,
+            vk::Extent2D { width: 1, height: 1 },
+            vk::ImageUsageFlags::empty(),
+        )?;
+        clear_images(device, command_pool, queue, &[*placeholder.image])?;
+
+        let mut graph = Self { desc, render_pass, sampler, placeholder, ..Default::default() };
+
+        for name in graph.desc.textures() {
+            let path = textures_dir.join(name);
+            let texture = Texture::load(instance, device, physical_device, allocator, command_pool, queue, &path)?;
+            graph.textures.push((name.to_string(), texture));
+        }
+
+        Ok(graph)
+    }
+
+    /// Creates the images and framebuffers of the buffer passes, cleared to black.
+    pub unsafe fn create_buffers(
+        &mut self,
+        device: &SharedDevice,
+        allocator: &MemoryAllocator,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        extent: vk::Extent2D,
+    ) -> Result<()> {
+        for _ in self.desc.buffers() {
+            let target = GraphImage::create(
+                device,
+                allocator,
+                extent,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            )?;
+            let history = GraphImage::create(device, allocator, extent, vk::ImageUsageFlags::empty())?;
+
+            let attachments = &[*target.view];
+            let info = vk::FramebufferCreateInfo::builder()
+                .render_pass(*self.render_pass)
+                .attachments(attachments)
+                .width(extent.width)
+                .height(extent.height)
+                .layers(1);
+
+            let framebuffer = Owned::new(device, device.create_framebuffer(&info, None)?);
+
+            self.buffers.push(BufferPass { target, history, framebuffer, descriptor_sets: Vec::new() });
+        }
+
+        let images = self.buffers
+            .iter()
+            .flat_map(|b| [*b.target.image, *b.history.image])
+            .collect::<Vec<_>>();
+        clear_images(device, command_pool, queue, &images)?;
+
+        Ok(())
+    }
+
+    /// The images and samplers bound to the channels of the pass at `pass_index`, the image pass comes after
+    /// the buffers.
+    pub fn channel_images(&self, pass_index: usize) -> [vk::DescriptorImageInfo; CHANNEL_COUNT] {
+        let image_info = |view, sampler| {
+            vk::DescriptorImageInfo::builder()
+                .image_view(view)
+                .sampler(sampler)
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .build()
+        };
+
+        let mut images = [image_info(*self.placeholder.view, *self.sampler); CHANNEL_COUNT];
+
+        let channels = &self.desc.passes[pass_index].channels;
+        for (image, channel) in images.iter_mut().zip(channels.iter()) {
+            let Some(name) = channel else {
+                continue;
+            };
+
+            if let Some((_, texture)) = self.textures.iter().find(|(n, _)| n == name) {
+                *image = image_info(*texture.view, *texture.sampler);
+            } else if let Some(buffer) = self.desc.buffer_index(name) {
+                // Passes that already ran this frame are sampled directly, the others through their history.
+                let view = if buffer < pass_index {
+                    *self.buffers[buffer].target.view
+                } else {
+                    *self.buffers[buffer].history.view
+                };
+                *image = image_info(view, *self.sampler);
+            }
+        }
+
+        images
+    }
+
+    /// Builds the pipelines of all passes from the current shaders, the image pass drawing in `render_pass` with
+    /// `samples` samples. Either every pipeline is built or none is kept, so a shader error leaves the previous
+    /// pipelines usable.
+    pub unsafe fn create_pipelines(
+        &self,
+        device: &SharedDevice,
+        pipeline_cache: vk::PipelineCache,
+        shader_manager: &ShaderManager,
+        descriptor_set_layout: &vk::DescriptorSetLayout,
+        render_pass: &vk::RenderPass,
+        samples: vk::SampleCountFlags,
+    ) -> Result<GraphPipelines> {
+        // The pipelines built before an error are dropped with `buffers`.
+        let mut buffers = Vec::new();
+
+        for pass in self.desc.buffers() {
+            let bytecode = shader_manager.get_shaders_bytecode(VERTEX_SHADER, &pass.fragment_shader, true)?;
+            let desc = PipelineDesc { depth: DepthOptions::DISABLED, ..PipelineDesc::new(bytecode) };
+            buffers.push(create_pipeline(device, pipeline_cache, &desc, descriptor_set_layout, &self.render_pass)?);
+        }
+
+        let vertex_shader = if self.desc.mesh.is_some() { MESH_VERTEX_SHADER } else { VERTEX_SHADER };
+        let bytecode = shader_manager.get_shaders_bytecode(vertex_shader, &self.desc.image().fragment_shader, false)?;
+        let desc = PipelineDesc {
+            cull_mode: self.desc.cull_mode,
+            polygon_mode: self.desc.polygon_mode,
+            blend: self.desc.blend,
+            depth: self.desc.depth,
+            samples,
+            ..PipelineDesc::new(bytecode)
+        };
+        let image = create_pipeline(device, pipeline_cache, &desc, descriptor_set_layout, render_pass)?;
+
+        Ok(GraphPipelines { image, buffers })
+    }
+
+    /// Records the buffer passes, each drawing the fullscreen `quad`.
+    pub unsafe fn record(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        extent: vk::Extent2D,
+        quad: &Mesh,
+        push_constants: &PushConstants,
+    ) {
+        for (pass, (pipeline_layout, pipeline)) in self.buffers.iter().zip(&self.pipelines) {
+            let render_area = vk::Rect2D::builder()
+                .offset(vk::Offset2D::default())
+                .extent(extent);
+
+            let info = vk::RenderPassBeginInfo::builder()
+                .render_pass(*self.render_pass)
+                .framebuffer(*pass.framebuffer)
+                .render_area(render_area);
+
+            device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE);
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, **pipeline);
+            record_viewport(device, command_buffer, extent);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                **pipeline_layout,
+                0,
+                &[pass.descriptor_sets[image_index]],
+                &[],
+            );
+            push_constants.record(device, command_buffer, **pipeline_layout);
+            quad.record_draw(device, command_buffer);
+            device.cmd_end_render_pass(command_buffer);
+
+            record_history_copy(device, command_buffer, pass, extent);
+        }
+    }
+
+    /// Drops the resources from `create_buffers`, the descriptor sets are freed with their pool. No frame using
+    /// them may be in flight.
+    pub fn destroy_buffers(&mut self) {
+        self.buffers.clear();
+    }
+}
+
+unsafe fn create_channel_sampler(device: &SharedDevice) -> Result<Sampler> {
+    let info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .anisotropy_enable(false)
+        .max_anisotropy(1.0)
+        .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
+        .unnormalized_coordinates(false)
+        .compare_enable(false)
+        .compare_op(vk::CompareOp::ALWAYS)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .mip_lod_bias(0.0)
+        .min_lod(0.0)
+        .max_lod(0.0);
+
+    Ok(Owned::new(device, device.create_sampler(&info, None)?))
+}
+
+fn color_subresource_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build()
+}
+
+fn image_barrier(
+    image: vk::Image,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    src_access_mask: vk::AccessFlags,
+    dst_access_mask: vk::AccessFlags,
+) -> vk::ImageMemoryBarrier {
+    vk::ImageMemoryBarrier::builder()
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(color_subresource_range())
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask)
+        .build()
+}
+
+/// Clears the images to black and leaves them ready to be sampled.
+unsafe fn clear_images(
+    device: &Device,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    images: &[vk::Image],
+) -> Result<()> {
+    if images.is_empty() {
+        return Ok(());
+    }
+
+    let command_buffer = begin_single_time_commands(device, command_pool)?;
+
+    let to_transfer = images
+        .iter()
+        .map(|i| image_barrier(
+            *i,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::TRANSFER_WRITE,
+        ))
+        .collect::<Vec<_>>();
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &to_transfer,
+    );
+
+    let black = vk::ClearColorValue { float32: [0.0, 0.0, 0.0, 0.0] };
+    for image in images {
+        device.cmd_clear_color_image(
+            command_buffer,
+            *image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &black,
+            &[color_subresource_range()],
+        );
+    }
+
+    let to_shader = images
+        .iter()
+        .map(|i| image_barrier(
+            *i,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::SHADER_READ,
+        ))
+        .collect::<Vec<_>>();
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::FRAGMENT_SHADER,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &to_shader,
+    );
+
+    end_single_time_commands(device, command_pool, queue, command_buffer)
+}
+
+/// Copies the target of a pass that just ran into its history, both end up ready to be sampled.
+unsafe fn record_history_copy(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    pass: &BufferPass,
+    extent: vk::Extent2D,
+) {
+    // The history's previous contents are replaced, but earlier passes of this frame may still be sampling it.
+    let to_transfer = [
+        image_barrier(
+            *pass.target.image,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            vk::AccessFlags::TRANSFER_READ,
+        ),
+        image_barrier(
+            *pass.history.image,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::TRANSFER_WRITE,
+        ),
+    ];
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::FRAGMENT_SHADER,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &to_transfer,
+    );
+
+    let subresource = vk::ImageSubresourceLayers::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(0)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    let region = vk::ImageCopy::builder()
+        .src_subresource(subresource)
+        .src_offset(vk::Offset3D::default())
+        .dst_subresource(subresource)
+        .dst_offset(vk::Offset3D::default())
+        .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 });
+
+    device.cmd_copy_image(
+        command_buffer,
+        *pass.target.image,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        *pass.history.image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[region],
+    );
+
+    let to_shader = [
+        image_barrier(
+            *pass.target.image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::SHADER_READ,
+        ),
+        image_barrier(
+            *pass.history.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::SHADER_READ,
+        ),
+    ];
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::FRAGMENT_SHADER,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &to_shader,
+    );
+}
@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use vulkanalia::prelude::v1_0::*;
+use vulkanalia::vk::{ExtDebugUtilsExtension, KhrSurfaceExtension, KhrSwapchainExtension};
+
+/// The instance with the surface and debug messenger created from it, destroyed when the last reference to it is
+/// dropped. Every `OwnedDevice` holds one, so the instance outlives its devices.
+#[derive(Debug)]
+pub struct OwnedInstance {
+    instance: Instance,
+    /// Null without validation.
+    pub messenger: vk::DebugUtilsMessengerEXT,
+    /// Null when rendering headless.
+    pub surface: vk::SurfaceKHR,
+    /// Keeps the Vulkan library loaded until the instance is destroyed.
+    _entry: Entry,
+}
+
+impl OwnedInstance {
+    pub fn new(entry: Entry, instance: Instance) -> Self {
+        Self {
+            instance,
+            messenger: vk::DebugUtilsMessengerEXT::null(),
+            surface: vk::SurfaceKHR::null(),
+            _entry: entry,
+        }
+    }
+}
+
+impl Deref for OwnedInstance {
+    type Target = Instance;
+
+    fn deref(&self) -> &Instance {
+        &self.instance
+    }
+}
+
+impl Drop for OwnedInstance {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.surface.is_null() {
+                self.instance.destroy_surface_khr(self.surface, None);
+            }
+            if !self.messenger.is_null() {
+                self.instance.destroy_debug_utils_messenger_ext(self.messenger, None);
+            }
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+pub type SharedInstance = Rc<OwnedInstance>;
+
+/// The logical device, destroyed when the last reference to it is dropped. Every object created from it holds
+/// one, so the device outlives them.
+#[derive(Debug)]
+pub struct OwnedDevice {
+    device: Device,
+    _instance: SharedInstance,
+}
+
+impl OwnedDevice {
+    pub fn new(instance: &SharedInstance, device: Device) -> Self {
+        Self { device, _instance: instance.clone() }
+    }
+}
+
+impl Deref for OwnedDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl Drop for OwnedDevice {
+    fn drop(&mut self) {
+        unsafe { self.device.destroy_device(None) };
+    }
+}
+
+pub type SharedDevice = Rc<OwnedDevice>;
+
+/// A handle destroyed through the device that created it, see `Owned`.
+pub trait DeviceHandle: vk::Handle {
+    unsafe fn destroy(self, device: &Device);
+}
+
+macro_rules! device_handle {
+    ($($handle:ty => $destroy:ident),* $(,)?) => {
+        $(impl DeviceHandle for $handle {
+            unsafe fn destroy(self, device: &Device) {
+                device.$destroy(self, None);
+            }
+        })*
+    };
+}
+
+device_handle! {
+    vk::CommandPool => destroy_command_pool,
+    vk::DescriptorPool => destroy_descriptor_pool,
+    vk::DescriptorSetLayout => destroy_descriptor_set_layout,
+    vk::Fence => destroy_fence,
+    vk::Framebuffer => destroy_framebuffer,
+    vk::ImageView => destroy_image_view,
+    vk::Pipeline => destroy_pipeline,
+    vk::PipelineCache => destroy_pipeline_cache,
+    vk::PipelineLayout => destroy_pipeline_layout,
+    vk::RenderPass => destroy_render_pass,
+    vk::Sampler => destroy_sampler,
+    vk::Semaphore => destroy_semaphore,
+    vk::ShaderModule => destroy_shader_module,
+    vk::SwapchainKHR => destroy_swapchain_khr,
+}
+
+/// Owns a handle and destroys it when dropped, which dereferences to the handle. The default holds a null handle
+/// and destroys nothing.
+///
+/// Objects allocated from a pool (command buffers, descriptor sets) and the swapchain images aren't owned, they
+/// go away with their pool or swapchain.
+pub struct Owned<T: DeviceHandle> {
+    handle: T,
+    device: Option<SharedDevice>,
+}
+
+impl<T: DeviceHandle> Owned<T> {
+    /// Takes ownership of a handle created from `device`.
+    pub fn new(device: &SharedDevice, handle: T) -> Self {
+        Self { handle, device: Some(device.clone()) }
+    }
+}
+
+impl<T: DeviceHandle> Default for Owned<T> {
+    fn default() -> Self {
+        Self { handle: T::default(), device: None }
+    }
+}
+
+impl<T: DeviceHandle> Deref for Owned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.handle
+    }
+}
+
+impl<T: DeviceHandle> fmt::Debug for Owned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Owned").field(&self.handle).finish()
+    }
+}
+
+impl<T: DeviceHandle> Drop for Owned<T> {
+    fn drop(&mut self) {
+        if let Some(device) = &self.device {
+            if !self.handle.is_null() {
+                unsafe { self.handle.destroy(device) };
+            }
+        }
+    }
+}
+
+pub type CommandPool = Owned<vk::CommandPool>;
+pub type DescriptorPool = Owned<vk::DescriptorPool>;
+pub type DescriptorSetLayout = Owned<vk::DescriptorSetLayout>;
+pub type Fence = Owned<vk::Fence>;
+pub type Framebuffer = Owned<vk::Framebuffer>;
+pub type ImageView = Owned<vk::ImageView>;
+pub type Pipeline = Owned<vk::Pipeline>;
+pub type PipelineCache = Owned<vk::PipelineCache>;
+pub type PipelineLayout = Owned<vk::PipelineLayout>;
+pub type RenderPass = Owned<vk::RenderPass>;
+pub type Sampler = Owned<vk::Sampler>;
+pub type Semaphore = Owned<vk::Semaphore>;
+pub type ShaderModule = Owned<vk::ShaderModule>;
+pub type Swapchain = Owned<vk::SwapchainKHR>;
+
+/// Keeps resources that frames still in flight may use alive until the GPU finished those frames.
+///
+/// Frames are counted as they're submitted. A resource deferred after `submitted` frames is dropped by `collect`
+/// once that many frames completed.
+#[derive(Debug, Default)]
+pub struct DeletionQueue {
+    pending: VecDeque<(u64, Box<dyn fmt::Debug>)>,
+}
+
+impl DeletionQueue {
+    pub fn defer(&mut self, submitted: u64, resource: impl fmt::Debug + 'static) {
+        self.pending.push_back((submitted, Box::new(resource)));
+    }
+
+    /// Drops the resources no frame after the first `completed` ones can use.
+    pub fn collect(&mut self, completed: u64) {
+        while self.pending.front().is_some_and(|(submitted, _)| *submitted <= completed) {
+            self.pending.pop_front();
+        }
+    }
+
+    /// Drops every resource, the device must be idle.
+    pub fn flush(&mut self) {
+        self.pending.clear();
+    }
+}
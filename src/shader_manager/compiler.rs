@@ -0,0 +1,169 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use naga::back::spv;
+use naga::front::glsl;
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::ShaderStage;
+use thiserror::Error;
+
+use super::prelude::*;
+
+/// A single problem reported by the GLSL frontend, located in the shader source.
+#[derive(Clone, Debug)]
+pub struct ShaderDiagnostic {
+    pub file: PathBuf,
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file.display(), self.line, self.column, self.message)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("Failed to read shader {file:?}: {source}")]
+    Io { file: PathBuf, source: std::io::Error },
+    #[error("Failed to compile shader:\n{}", .0.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n"))]
+    Compile(Vec<ShaderDiagnostic>),
+    #[error("Shader {file:?} is invalid: {message}")]
+    Validation { file: PathBuf, message: String },
+    #[error("Failed to generate SPIR-V for {file:?}: {message}")]
+    Codegen { file: PathBuf, message: String },
+}
+
+impl ShaderError {
+    /// A one line description of the error, the first diagnostic for compile errors.
+    pub fn summary(&self) -> String {
+        match self {
+            ShaderError::Compile(diagnostics) if !diagnostics.is_empty() => diagnostics[0].to_string(),
+            error => error.to_string().lines().next().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Moves the diagnostics up by `lines`, to point back into the source before a prelude was added.
+    fn without_prelude(self, lines: u32) -> Self {
+        match self {
+            ShaderError::Compile(diagnostics) => ShaderError::Compile(
+                diagnostics
+                    .into_iter()
+                    .map(|d| ShaderDiagnostic { line: d.line.saturating_sub(lines).max(1), ..d })
+                    .collect(),
+            ),
+            error => error,
+        }
+    }
+}
+
+/// The pipeline stage of a shader, derived from its file extension. Only the stages of the graphics pipelines the
+/// engine builds are recognized, so edits to other shaders don't trigger a reload.
+pub fn shader_stage(file: &Path) -> Option<ShaderStage> {
+    match file.extension()?.to_str()? {
+        "vert" => Some(ShaderStage::Vertex),
+        "frag" => Some(ShaderStage::Fragment),
+        _ => None,
+    }
+}
+
+/// Reads and compiles a GLSL shader file to SPIR-V words. Shadertoy style fragment shaders get the prelude,
+/// `offscreen` tells whether they draw into a render graph buffer rather than the screen.
+pub fn compile_file(file: &Path, stage: ShaderStage, offscreen: bool) -> Result<Vec<u32>, ShaderError> {
+    let source = fs::read_to_string(file)
+        .map_err(|source| ShaderError::Io { file: file.to_path_buf(), source })?;
+
+    if stage == ShaderStage::Fragment && is_shadertoy(&source) {
+        return compile_source(file, &wrap_shadertoy(&source, offscreen), stage)
+            .map_err(|error| error.without_prelude(prelude_lines()));
+    }
+
+    compile_source(file, &source, stage)
+}
+
+/// Compiles GLSL source to SPIR-V words, `file` is only used to locate errors.
+pub fn compile_source(file: &Path, source: &str, stage: ShaderStage) -> Result<Vec<u32>, ShaderError> {
+    let module = glsl::Frontend::default()
+        .parse(&glsl::Options::from(stage), source)
+        .map_err(|errors| {
+            ShaderError::Compile(
+                errors
+                    .iter()
+                    .map(|error| {
+                        let location = error.meta.location(source);
+                        ShaderDiagnostic {
+                            file: file.to_path_buf(),
+                            line: location.line_number,
+                            column: location.line_position,
+                            message: error.kind.to_string(),
+                        }
+                    })
+                    .collect(),
+            )
+        })?;
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|error| ShaderError::Validation {
+            file: file.to_path_buf(),
+            message: error.emit_to_string(source),
+        })?;
+
+    spv::write_vec(&module, &info, &spv::Options::default(), None)
+        .map_err(|error| ShaderError::Codegen { file: file.to_path_buf(), message: error.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_shaders_compile() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("shaders");
+        let mut compiled = 0;
+
+        for entry in fs::read_dir(dir).unwrap() {
+            let file = entry.unwrap().path();
+            let Some(stage) = shader_stage(&file) else {
+                continue;
+            };
+
+            if let Err(error) = compile_file(&file, stage, false) {
+                panic!("{}", error);
+            }
+            compiled += 1;
+        }
+
+        assert!(compiled > 0);
+    }
+
+    fn compile_error(name: &str, source: &str) -> ShaderDiagnostic {
+        let file = std::env::temp_dir().join(format!("{}_{}.frag", name, std::process::id()));
+        fs::write(&file, source).unwrap();
+        let result = compile_file(&file, ShaderStage::Fragment, false);
+        fs::remove_file(&file).unwrap();
+
+        match result {
+            Err(ShaderError::Compile(diagnostics)) => {
+                assert_eq!(diagnostics[0].file, file);
+                diagnostics[0].clone()
+            }
+            result => panic!("Expected a compile error, got {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn compile_errors_point_into_the_source() {
+        let source = "#version 450\n\nlayout(location = 0) out vec4 color;\n\nvoid main() {\n    color = missing;\n}\n";
+        assert_eq!(compile_error("plain", source).line, 6);
+    }
+
+    #[test]
+    fn shadertoy_compile_errors_skip_the_prelude() {
+        let source = "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    fragColor = missing;\n}\n";
+        assert_eq!(compile_error("shadertoy", source).line, 2);
+    }
+}
@@ -1,68 +1,132 @@
-use std::{process::Command, path::{PathBuf, Path}, fs};
-use anyhow::{Result, Ok};
+use std::{path::{PathBuf, Path}, fs, rc::Rc};
+use anyhow::{anyhow, Result};
 use log::*;
+use naga::ShaderStage;
 
+use crate::render_graph::RenderGraphDesc;
+
+mod compiler;
+pub use compiler::*;
+
+mod watcher;
+use watcher::*;
+
+mod prelude;
+
+/// The vertex shader of the fullscreen passes.
+pub const VERTEX_SHADER: &str = "shader.vert";
+/// The vertex shader of an image pass drawing a mesh, placing it in front of a camera.
+pub const MESH_VERTEX_SHADER: &str = "mesh.vert";
+const DEFAULT_FRAGMENT_SHADER: &str = "shader.frag";
+
+/// SPIR-V words of the shaders making up the graphics pipeline.
+#[derive(Clone, Debug, Default)]
 pub struct ShaderByteCode {
-    pub vertex: Vec<u8>,
-    pub fragment: Vec<u8>
+    pub vertex: Vec<u32>,
+    pub fragment: Vec<u32>
 }
 
 #[derive(Clone, Debug)]
 pub struct ShaderManager {
-    shaders_dir: PathBuf
+    shaders_dir: PathBuf,
+    fragment_shader: String,
+    /// A `.scene` file in the shaders dir describing a multi-pass render graph, used instead of `fragment_shader`.
+    scene: Option<String>,
+    watcher: Option<Rc<ShaderWatcher>>
 }
 
 impl ShaderManager {
+    /// A shader manager for the shaders dir from `SHADER_DIR`, see `find_shaders_path`.
     pub fn create() -> Result<Self> {
-        let shaders_dir = find_shaders_path()?;
-
-        Ok( Self { shaders_dir })
+        Self::create_in(&find_shaders_path()?)
     }
 
-    fn convert_shaders(&self) {
-        let result = Command::new("sh")
-            .current_dir(&self.shaders_dir)
-            .arg("./compile.sh")
-            .output();
-    
-        if result.is_err() {
-            error!("Shader compiler error:\n{:#?}", result.as_ref().err());
-        }
-    
-        let output = result.unwrap();
-    
-        if output.stderr.is_empty() == false {
-            info!("Shader compilation failed: {:#?}", output);
+    pub fn create_in(shaders_dir: &Path) -> Result<Self> {
+        if !shaders_dir.is_dir() {
+            return Err(anyhow!("The dir containing the shaders {:?} doesn't exist.", shaders_dir));
         }
+        info!("Shaders dir: {:?}", shaders_dir);
+
+        Ok( Self { shaders_dir: shaders_dir.to_path_buf(), fragment_shader: DEFAULT_FRAGMENT_SHADER.to_string(), scene: None, watcher: None })
     }
 
-    pub fn get_shaders_bytecode(&self) -> Result<ShaderByteCode> {
-        self.convert_shaders();
+    /// Starts watching the shaders dir, see `poll_changes`.
+    pub fn watch(&mut self) -> Result<()> {
+        self.watcher = Some(Rc::new(ShaderWatcher::create(&self.shaders_dir)?));
 
-        let vert = fs::read(self.shaders_dir.join("vert.spv"))?;
-        let frag = fs::read(self.shaders_dir.join("frag.spv"))?;
+        Ok(())
+    }
 
-        Ok( ShaderByteCode { vertex: vert, fragment: frag } )
+    /// True if a shader source changed since the last call, always false when not watching.
+    pub fn poll_changes(&self) -> bool {
+        self.watcher.as_ref().is_some_and(|w| w.poll_changes())
     }
 
+    /// Uses the given fragment shader (a file name in the shaders dir) instead of `shader.frag`.
+    pub fn with_fragment_shader(mut self, fragment_shader: &str) -> Self {
+        self.fragment_shader = fragment_shader.to_string();
+        self
+    }
 
-}
+    /// Renders the given scene (a file name in the shaders dir) instead of a single fragment shader.
+    pub fn with_scene(mut self, scene: &str) -> Self {
+        self.scene = Some(scene.to_string());
+        self
+    }
 
-fn find_shaders_path() -> Result<PathBuf> {
-    let result = std::env::var("SHADER_DIR");
+    pub fn shaders_dir(&self) -> &Path {
+        &self.shaders_dir
+    }
 
-    let dir: PathBuf;
+    /// The file names of all fragment shaders in the shaders dir, sorted.
+    pub fn fragment_shaders(&self) -> Result<Vec<String>> {
+        self.files_with_extension(".frag")
+    }
 
-    if result.is_ok() {
-        let path = result.unwrap();
-        dir = Path::new(&path).to_path_buf()
-    } else {
-        error!("SHADER_DIR env variable is not set, defaulting to ./shaders/");
-        dir = std::env::current_dir()?.join("shaders/");
+    /// The file names of all scenes in the shaders dir, sorted.
+    pub fn scenes(&self) -> Result<Vec<String>> {
+        self.files_with_extension(".scene")
     }
-    
-    assert!(dir.exists(), "The dir containing the shaders: '{:?}' doesn't exist, exiting.", dir);
-    info!("Shaders dir: {:?}", dir);
+
+    fn files_with_extension(&self, extension: &str) -> Result<Vec<String>> {
+        let mut files = fs::read_dir(&self.shaders_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(extension))
+            .collect::<Vec<_>>();
+
+        files.sort();
+
+        Ok(files)
+    }
+
+    /// The passes to render, loaded from the selected scene or made of only the selected fragment shader.
+    pub fn render_graph_desc(&self) -> Result<RenderGraphDesc> {
+        match &self.scene {
+            Some(scene) => RenderGraphDesc::load(&self.shaders_dir.join(scene)),
+            None => Ok(RenderGraphDesc::single(&self.fragment_shader)),
+        }
+    }
+
+    /// Compiles the given vertex and fragment shader to SPIR-V, `offscreen` for render graph buffers.
+    pub fn get_shaders_bytecode(&self, vertex_shader: &str, fragment_shader: &str, offscreen: bool) -> Result<ShaderByteCode, ShaderError> {
+        let vertex = compile_file(&self.shaders_dir.join(vertex_shader), ShaderStage::Vertex, false)?;
+        let fragment = compile_file(&self.shaders_dir.join(fragment_shader), ShaderStage::Fragment, offscreen)?;
+
+        debug!("Compiled {} and {}", vertex_shader, fragment_shader);
+
+        Ok( ShaderByteCode { vertex, fragment } )
+    }
+}
+
+/// The shaders dir from the `SHADER_DIR` env variable, `./shaders/` if it isn't set.
+pub fn find_shaders_path() -> Result<PathBuf> {
+    let dir = if let Ok(path) = std::env::var("SHADER_DIR") {
+        Path::new(&path).to_path_buf()
+    } else {
+        warn!("SHADER_DIR env variable is not set, defaulting to ./shaders/");
+        std::env::current_dir()?.join("shaders/")
+    };
 
     Ok(dir)
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+//! Lets fragment shaders pasted from Shadertoy compile unchanged.
+//!
+//! Shadertoy sources have no `#version` and define `mainImage` instead of `main`, so those get the inputs
+//! declared in front and a `main` calling `mainImage` appended.
+//!
+//! The channels are separate image and sampler bindings (see `create_descriptor_set_layout`), `iChannel0` to
+//! `iChannel3` are defined as the combined `sampler2D` so `texture(iChannel0, uv)` works as usual.
+
+/// Declares the Shadertoy inputs, must match `PushConstants` and `UniformBufferObject`.
+const PRELUDE: &str = "#version 450
+
+layout(location = 0) out vec4 shadertoy_FragColor;
+
+layout(push_constant) uniform ShadertoyFrame {
+    vec3 iResolution;
+    float iTime;
+    int iFrame;
+};
+
+layout(binding = 0) uniform ShadertoyInputs {
+    float iTimeDelta;
+    float iFrameRate;
+    uint iMouseButtons;
+    vec4 iMouse;
+    vec4 iDate;
+    vec2 iCursor;
+    uvec4 iKeyboard[6];
+};
+
+// Row 0 holds the keys that are down, row 1 the keys pressed this frame and row 2 the toggled keys,
+// indexed by JavaScript key code like Shadertoy's keyboard texture.
+bool shadertoy_key(int key, int row) {
+    int bit = row * 256 + key;
+    return (iKeyboard[bit / 128][(bit / 32) % 4] & (1u << uint(bit % 32))) != 0u;
+}
+
+bool keyDown(int key) { return shadertoy_key(key, 0); }
+bool keyPressed(int key) { return shadertoy_key(key, 1); }
+bool keyToggled(int key) { return shadertoy_key(key, 2); }
+
+layout(binding = 1) uniform texture2D shadertoy_Channel0;
+layout(binding = 2) uniform texture2D shadertoy_Channel1;
+layout(binding = 3) uniform texture2D shadertoy_Channel2;
+layout(binding = 4) uniform texture2D shadertoy_Channel3;
+layout(binding = 5) uniform sampler shadertoy_Sampler0;
+layout(binding = 6) uniform sampler shadertoy_Sampler1;
+layout(binding = 7) uniform sampler shadertoy_Sampler2;
+layout(binding = 8) uniform sampler shadertoy_Sampler3;
+
+#define iChannel0 sampler2D(shadertoy_Channel0, shadertoy_Sampler0)
+#define iChannel1 sampler2D(shadertoy_Channel1, shadertoy_Sampler1)
+#define iChannel2 sampler2D(shadertoy_Channel2, shadertoy_Sampler2)
+#define iChannel3 sampler2D(shadertoy_Channel3, shadertoy_Sampler3)
+";
+
+/// Calls `mainImage` with Shadertoy's bottom left origin for `fragCoord` when drawing to the screen.
+const SCREEN_EPILOGUE: &str = "
+void main() {
+    mainImage(shadertoy_FragColor, vec2(gl_FragCoord.x, iResolution.y - gl_FragCoord.y));
+}
+";
+
+/// Offscreen buffers keep the framebuffer origin, so the first row of the image is `fragCoord.y == 0` and
+/// sampling it at `fragCoord / iResolution.xy` lines up like it does on Shadertoy.
+const OFFSCREEN_EPILOGUE: &str = "
+void main() {
+    mainImage(shadertoy_FragColor, gl_FragCoord.xy);
+}
+";
+
+/// True for Shadertoy style sources, which define `mainImage` and have no `#version` directive. Only a line
+/// starting with `#version` is a directive, a mention in a comment doesn't count.
+pub fn is_shadertoy(source: &str) -> bool {
+    source.contains("mainImage") && !source.lines().any(|line| line.trim_start().starts_with("#version"))
+}
+
+/// Wraps a Shadertoy style source with the prelude and a `main`, for a pass drawing offscreen or to the screen.
+pub fn wrap_shadertoy(source: &str, offscreen: bool) -> String {
+    let epilogue = if offscreen { OFFSCREEN_EPILOGUE } else { SCREEN_EPILOGUE };
+
+    format!("{}{}\n{}", PRELUDE, source, epilogue)
+}
+
+/// The number of lines the prelude shifts the original source by.
+pub fn prelude_lines() -> u32 {
+    PRELUDE.lines().count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADERTOY: &str = "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    fragColor = vec4(1.0);\n}\n";
+
+    #[test]
+    fn shadertoy_sources_have_main_image_and_no_version() {
+        assert!(is_shadertoy(SHADERTOY));
+        assert!(!is_shadertoy("#version 450\nvoid mainImage(out vec4 c, in vec2 p) {}\nvoid main() {}\n"));
+        assert!(!is_shadertoy("  #version 450\nvoid mainImage(out vec4 c, in vec2 p) {}\n"));
+        assert!(!is_shadertoy("#version 450\nvoid main() {}\n"));
+    }
+
+    #[test]
+    fn version_in_a_comment_isnt_a_directive() {
+        let source = format!("// Shadertoy style: no #version and a mainImage.\n{}", SHADERTOY);
+        assert!(is_shadertoy(&source));
+
+        let source = format!("/* #version 450 */\n{}", SHADERTOY);
+        assert!(is_shadertoy(&source));
+    }
+
+    #[test]
+    fn wrap_puts_the_source_after_the_prelude() {
+        let wrapped = wrap_shadertoy(SHADERTOY, false);
+        let lines = wrapped.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "#version 450");
+        assert_eq!(lines[prelude_lines() as usize], SHADERTOY.lines().next().unwrap());
+        assert!(wrapped.contains("iResolution.y - gl_FragCoord.y"));
+    }
+
+    #[test]
+    fn offscreen_wrap_keeps_the_framebuffer_origin() {
+        let wrapped = wrap_shadertoy(SHADERTOY, true);
+
+        assert!(wrapped.contains("mainImage(shadertoy_FragColor, gl_FragCoord.xy);"));
+        assert!(!wrapped.contains("iResolution.y - gl_FragCoord.y"));
+    }
+
+    #[test]
+    fn prelude_lines_counts_the_prelude() {
+        assert_eq!(prelude_lines() as usize, PRELUDE.lines().count());
+        assert!(PRELUDE.ends_with('\n'));
+    }
+}
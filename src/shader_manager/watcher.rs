@@ -0,0 +1,52 @@
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+
+use anyhow::Result;
+use log::*;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::compiler::shader_stage;
+
+/// Watches a directory for changes to shader sources.
+#[derive(Debug)]
+pub struct ShaderWatcher {
+    // Kept alive for as long as events should be delivered.
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl ShaderWatcher {
+    pub fn create(dir: &Path) -> Result<Self> {
+        let (sender, events) = channel();
+
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiver only goes away together with the watcher.
+            sender.send(event).ok();
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        info!("Watching {:?} for shader changes", dir);
+
+        Ok(Self { _watcher: watcher, events })
+    }
+
+    /// Drains the pending events, true if any `.vert`/`.frag` file was created or modified since the last call.
+    pub fn poll_changes(&self) -> bool {
+        let mut changed = false;
+
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    if event.paths.iter().any(|p| shader_stage(p).is_some()) {
+                        debug!("Shader source changed: {:?}", event.paths);
+                        changed = true;
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => warn!("Shader watcher error: {}", error),
+            }
+        }
+
+        changed
+    }
+}
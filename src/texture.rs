@@ -0,0 +1,274 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::ptr::copy_nonoverlapping as memcpy;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::allocator::*;
+use crate::buffers::common::create_buffer;
+use crate::create_image::*;
+use crate::resources::*;
+use crate::single_time_commands::*;
+
+/// Textures hold data like noise and lookup tables rather than colors, so they are sampled without sRGB decoding.
+pub const TEXTURE_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+/// A sampled image loaded from a PNG, with a full mip chain and its own repeating sampler.
+#[derive(Debug, Default)]
+pub struct Texture {
+    pub view: ImageView,
+    pub sampler: Sampler,
+    pub image: Image,
+    pub extent: vk::Extent2D,
+    pub mip_levels: u32,
+}
+
+impl Texture {
+    /// Loads a PNG through a staging buffer into a device local image and generates its mipmaps.
+    pub unsafe fn load(
+        instance: &Instance,
+        device: &SharedDevice,
+        physical_device: &vk::PhysicalDevice,
+        allocator: &MemoryAllocator,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        path: &Path,
+    ) -> Result<Self> {
+        let (extent, pixels) = read_png(path)?;
+        let mip_levels = extent.width.max(extent.height).ilog2() + 1;
+
+        debug!("Loading {}x{} texture {:?} with {} mip levels", extent.width, extent.height, path, mip_levels);
+
+        let properties = instance.get_physical_device_format_properties(*physical_device, TEXTURE_FORMAT);
+        if !properties.optimal_tiling_features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+            return Err(anyhow!("Texture format {:?} doesn't support linear blitting for mipmaps.", TEXTURE_FORMAT));
+        }
+
+        let size = pixels.len() as vk::DeviceSize;
+        let staging_buffer = create_buffer(
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        memcpy(pixels.as_ptr(), staging_buffer.allocation().mapped_ptr()?, pixels.len());
+
+        let image = create_image(
+            allocator,
+            extent,
+            mip_levels,
+            vk::SampleCountFlags::_1,
+            TEXTURE_FORMAT,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        upload_with_mipmaps(device, command_pool, queue, *staging_buffer, *image, extent, mip_levels)?;
+
+        let view = create_image_view(device, *image, TEXTURE_FORMAT, vk::ImageAspectFlags::COLOR, mip_levels)?;
+        let sampler = create_texture_sampler(device, mip_levels)?;
+
+        Ok(Self { view, sampler, image, extent, mip_levels })
+    }
+}
+
+/// Reads a PNG as tightly packed RGBA8 pixels.
+pub fn read_png(path: &Path) -> Result<(vk::Extent2D, Vec<u8>)> {
+    let file = File::open(path).map_err(|e| anyhow!("Failed to open {:?}: {}", path, e))?;
+
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|p| [*p, *p, *p, 255]).collect(),
+        png::ColorType::Indexed => return Err(anyhow!("Unexpanded indexed PNG {:?}.", path)),
+    };
+
+    Ok((vk::Extent2D { width: info.width, height: info.height }, pixels))
+}
+
+/// Copies the staging buffer into the first mip level and blits each level into the next, halving its size.
+/// All levels end up ready to be sampled.
+unsafe fn upload_with_mipmaps(
+    device: &Device,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    staging_buffer: vk::Buffer,
+    image: vk::Image,
+    extent: vk::Extent2D,
+    mip_levels: u32,
+) -> Result<()> {
+    let command_buffer = begin_single_time_commands(device, command_pool)?;
+
+    let barrier = |mip_level: u32, level_count: u32, old_layout, new_layout, src_access_mask, dst_access_mask| {
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(mip_level)
+            .level_count(level_count)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .build()
+    };
+
+    let pipeline_barrier = |src_stage_mask, dst_stage_mask, barrier: vk::ImageMemoryBarrier| {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage_mask,
+            dst_stage_mask,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[barrier],
+        );
+    };
+
+    pipeline_barrier(
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::PipelineStageFlags::TRANSFER,
+        barrier(
+            0,
+            mip_levels,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::TRANSFER_WRITE,
+        ),
+    );
+
+    let subresource = |mip_level| {
+        vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(mip_level)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build()
+    };
+
+    let region = vk::BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(0)
+        .buffer_image_height(0)
+        .image_subresource(subresource(0))
+        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+        .image_extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 });
+
+    device.cmd_copy_buffer_to_image(
+        command_buffer,
+        staging_buffer,
+        image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[region],
+    );
+
+    let mut width = extent.width as i32;
+    let mut height = extent.height as i32;
+
+    for level in 1..mip_levels {
+        pipeline_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::TRANSFER,
+            barrier(
+                level - 1,
+                1,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::TRANSFER_READ,
+            ),
+        );
+
+        let next_width = (width / 2).max(1);
+        let next_height = (height / 2).max(1);
+
+        let blit = vk::ImageBlit::builder()
+            .src_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, vk::Offset3D { x: width, y: height, z: 1 }])
+            .src_subresource(subresource(level - 1))
+            .dst_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, vk::Offset3D { x: next_width, y: next_height, z: 1 }])
+            .dst_subresource(subresource(level));
+
+        device.cmd_blit_image(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[blit],
+            vk::Filter::LINEAR,
+        );
+
+        pipeline_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            barrier(
+                level - 1,
+                1,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::AccessFlags::TRANSFER_READ,
+                vk::AccessFlags::SHADER_READ,
+            ),
+        );
+
+        width = next_width;
+        height = next_height;
+    }
+
+    // The last level was only written to.
+    pipeline_barrier(
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::FRAGMENT_SHADER,
+        barrier(
+            mip_levels - 1,
+            1,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::SHADER_READ,
+        ),
+    );
+
+    end_single_time_commands(device, command_pool, queue, command_buffer)
+}
+
+unsafe fn create_texture_sampler(device: &SharedDevice, mip_levels: u32) -> Result<Sampler> {
+    let info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::REPEAT)
+        .address_mode_v(vk::SamplerAddressMode::REPEAT)
+        .address_mode_w(vk::SamplerAddressMode::REPEAT)
+        .anisotropy_enable(false)
+        .max_anisotropy(1.0)
+        .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
+        .unnormalized_coordinates(false)
+        .compare_enable(false)
+        .compare_op(vk::CompareOp::ALWAYS)
+        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+        .mip_lod_bias(0.0)
+        .min_lod(0.0)
+        .max_lod(mip_levels as f32);
+
+    Ok(Owned::new(device, device.create_sampler(&info, None)?))
+}
@@ -1,15 +1,90 @@
+use nalgebra_glm as glm;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The Shadertoy inputs, laid out to match the std140 `ShadertoyInputs` block declared by the shader prelude.
+///
+/// `iResolution`, `iTime` and `iFrame` are push constants instead (see `PushConstants`).
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct UniformBufferObject {
-    pub time: f32,
-    pub width: f32,
-    pub height: f32,
-    // pub resolution: nalgebra_glm::Vec2
-}
-
-// #[repr(C)]
-// #[derive(Copy, Clone, Debug)]
-// pub struct Vec2 {
-//     x: f32,
-//     y: f32
-// }
\ No newline at end of file
+    /// `iTimeDelta`, seconds the previous frame took.
+    pub time_delta: f32,
+    /// `iFrameRate`, frames per second.
+    pub frame_rate: f32,
+    /// `iMouseButtons`, a bit per held mouse button (see `MouseState::buttons`).
+    pub mouse_buttons: u32,
+    pub _padding0: f32,
+    /// `iMouse`, xy is the cursor while the left button is held, zw the click position (see `MouseState`).
+    pub mouse: glm::Vec4,
+    /// `iDate`, the year, month (starting at 0), day and seconds since midnight. Unlike Shadertoy, which uses the
+    /// browser's local time, this is UTC (see `current_date`).
+    pub date: glm::Vec4,
+    /// `iCursor`, the cursor position whether or not a button is held.
+    pub cursor: glm::Vec2,
+    pub _padding1: [f32; 2],
+    /// `iKeyboard`, the down, pressed and toggled key rows (see `KeyboardState::uniform`).
+    pub keyboard: [[u32; 4]; 6],
+}
+
+impl UniformBufferObject {
+    /// Inputs for a still frame, as used by headless rendering.
+    pub fn still() -> Self {
+        Self {
+            time_delta: 0.0,
+            frame_rate: 0.0,
+            mouse_buttons: 0,
+            _padding0: 0.0,
+            mouse: glm::Vec4::zeros(),
+            date: glm::Vec4::zeros(),
+            cursor: glm::Vec2::zeros(),
+            _padding1: [0.0; 2],
+            keyboard: [[0; 4]; 6],
+        }
+    }
+}
+
+/// The `iDate` value for the current time, in UTC.
+///
+/// Shadertoy fills `iDate` with local time. The standard library has no time zone support, so clock shaders show
+/// UTC here and are off by the local offset.
+pub fn current_date() -> glm::Vec4 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+
+    let days = (since_epoch / 86400.0).floor() as i64;
+    let seconds = since_epoch - days as f64 * 86400.0;
+    let (year, month, day) = civil_from_days(days);
+
+    glm::vec4(year as f32, (month - 1) as f32, day as f32, seconds as f32)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date, months and days starting at 1.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_since_the_epoch_become_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+}
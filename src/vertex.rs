@@ -4,15 +4,16 @@ use std::mem::size_of;
 use vulkanalia::prelude::v1_0::*;
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Vertex {
-    pos: glm::Vec2,
-    color: glm::Vec3,
+    pos: glm::Vec3,
+    normal: glm::Vec3,
+    uv: glm::Vec2,
 }
 
 impl Vertex {
-    pub fn new(pos: glm::Vec2, color: glm::Vec3) -> Self {
-        Self { pos, color }
+    pub fn new(pos: glm::Vec3, normal: glm::Vec3, uv: glm::Vec2) -> Self {
+        Self { pos, normal, uv }
     }
 
     pub fn binding_description() -> vk::VertexInputBindingDescription {
@@ -23,33 +24,40 @@ impl Vertex {
             .build()
     }
 
-    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 3] {
         let pos = vk::VertexInputAttributeDescription::builder()
             .binding(0)
             .location(0)
-            .format(vk::Format::R32G32_SFLOAT)
+            .format(vk::Format::R32G32B32_SFLOAT)
             .offset(0)
             .build();
 
-        let color = vk::VertexInputAttributeDescription::builder()
+        let normal = vk::VertexInputAttributeDescription::builder()
             .binding(0)
             .location(1)
             .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(size_of::<glm::Vec2>() as u32)
+            .offset(size_of::<glm::Vec3>() as u32)
+            .build();
+
+        let uv = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(2)
+            .format(vk::Format::R32G32_SFLOAT)
+            .offset((size_of::<glm::Vec3>() * 2) as u32)
             .build();
-        
-        return [pos, color];
+
+        [pos, normal, uv]
     }
 }
 
 lazy_static! {
+    /// The fullscreen quad the fragment shaders are drawn on, facing the viewer.
     pub static ref VERTICES: Vec<Vertex> = vec![
-        Vertex::new(glm::vec2(-1.0, -1.0), glm::vec3(1.0, 0.0, 0.0)),
-        Vertex::new(glm::vec2(1.0, 1.0), glm::vec3(0.0, 1.0, 0.0)),
-        Vertex::new(glm::vec2(-1.0, 1.0), glm::vec3(0.0, 0.0, 1.0)),
-
-        Vertex::new(glm::vec2(-1.0, -1.0), glm::vec3(1.0, 0.0, 0.0)),
-        Vertex::new(glm::vec2(1.0, -1.0), glm::vec3(1.0, 0.0, 0.0)),
-        Vertex::new(glm::vec2(1.0, 1.0), glm::vec3(0.0, 1.0, 0.0)),
+        Vertex::new(glm::vec3(-1.0, -1.0, 0.0), glm::vec3(0.0, 0.0, 1.0), glm::vec2(0.0, 0.0)),
+        Vertex::new(glm::vec3(1.0, -1.0, 0.0), glm::vec3(0.0, 0.0, 1.0), glm::vec2(1.0, 0.0)),
+        Vertex::new(glm::vec3(1.0, 1.0, 0.0), glm::vec3(0.0, 0.0, 1.0), glm::vec2(1.0, 1.0)),
+        Vertex::new(glm::vec3(-1.0, 1.0, 0.0), glm::vec3(0.0, 0.0, 1.0), glm::vec2(0.0, 1.0)),
     ];
-}
\ No newline at end of file
+}
+
+pub const INDICES: &[u32] = &[0, 2, 3, 0, 1, 2];
@@ -0,0 +1,39 @@
+use std::path::Path;
+use std::process::Command;
+
+use vulkanalia::loader::{LibloadingLoader, LIBRARY};
+
+/// Renders every fragment shader in `shaders/` offscreen and compares it against `shaders/golden/`.
+///
+/// Needs a Vulkan driver (a software one like lavapipe is enough), so it only runs with `cargo test -- --ignored`.
+/// Set `GOLDEN_TOLERANCE` to override the allowed per-channel difference, `GOLDEN_BLESS=1` to rewrite the references.
+#[test]
+#[ignore = "needs a Vulkan driver, run with --ignored"]
+fn shaders_match_golden_images() {
+    if let Err(error) = unsafe { LibloadingLoader::new(LIBRARY) } {
+        panic!("No Vulkan loader: {}", error);
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rust_vulkan_engine"));
+    command
+        .env("SHADER_DIR", root.join("shaders"))
+        .args(["--golden", root.join("shaders/golden").to_str().unwrap()])
+        .args(["--size", "256x256", "--time", "1.0"]);
+
+    if let Ok(tolerance) = std::env::var("GOLDEN_TOLERANCE") {
+        command.args(["--tolerance", &tolerance]);
+    }
+
+    if std::env::var("GOLDEN_BLESS").is_ok() {
+        command.arg("--bless");
+    }
+
+    let output = command.output().expect("Failed to run the engine binary.");
+
+    assert!(
+        output.status.success(),
+        "Golden image run failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}